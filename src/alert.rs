@@ -0,0 +1,523 @@
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::{
+    anomaly::Anomaly,
+    budget::BudgetOverrun,
+    credit::{Balance, CreditShortfall},
+    estimate::{EstimateReason, EstimatedReading},
+    kplc::KPLCBill,
+    payment::Payment,
+    tariff::Discrepancy,
+};
+
+/// How urgently an alert should grab the recipient's attention.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Normal,
+    High,
+    Critical,
+}
+
+/// What an alert is about.
+#[derive(Debug, Clone)]
+pub enum AlertKind {
+    /// A balance is owed on the account.
+    BalanceDue,
+    /// A payment reached KPLC since the previous run.
+    PaymentReceived(Payment),
+    /// The latest bill is well above what's usually billed.
+    UnusualBill(Anomaly),
+    /// A meter's latest reading looks estimated rather than actually read.
+    EstimatedReading(EstimatedReading),
+    /// The latest bill doesn't match the tariff for the kWh consumed.
+    BillDiscrepancy(Discrepancy),
+    /// The latest bill, or the one projected to follow it, is over budget.
+    OverBudget(BudgetOverrun),
+    /// Credit on the account won't cover the projected next bill.
+    LowCredit(CreditShortfall),
+}
+
+/// An alert about a bill, rendered the same way by every channel.
+pub struct Alert<'a> {
+    pub kind: AlertKind,
+    pub bill: &'a KPLCBill,
+    /// Extra lines of context appended to the message.
+    pub notes: Vec<String>,
+}
+
+impl<'a> Alert<'a> {
+    pub fn new(kind: AlertKind, bill: &'a KPLCBill) -> Alert<'a> {
+        Alert {
+            kind,
+            bill,
+            notes: vec![],
+        }
+    }
+
+    pub fn with_notes(mut self, notes: Vec<String>) -> Alert<'a> {
+        self.notes.extend(notes);
+        self
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            AlertKind::BalanceDue if self.bill.is_overdue(Utc::now()) => Severity::Critical,
+            AlertKind::BalanceDue => Severity::Normal,
+            AlertKind::PaymentReceived(_) => Severity::Low,
+            AlertKind::UnusualBill(_) => Severity::High,
+            AlertKind::EstimatedReading(_) => Severity::Normal,
+            AlertKind::BillDiscrepancy(_) => Severity::High,
+            AlertKind::OverBudget(_) => Severity::High,
+            AlertKind::LowCredit(_) => Severity::Normal,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        let account_ref = self.bill.data.account_reference.as_str();
+
+        match &self.kind {
+            AlertKind::BalanceDue => match self.bill.data.col_bills.first() {
+                Some(latest_bill) => {
+                    let billing_period = latest_bill.billing_period.as_str();
+
+                    format!("KPLC Bill (#{account_ref}): {billing_period}")
+                }
+                None => format!("KPLC Bill (#{account_ref})"),
+            },
+            AlertKind::PaymentReceived(_) => format!("KPLC Payment Received (#{account_ref})"),
+            AlertKind::UnusualBill(anomaly) => {
+                let billing_period = anomaly.billing_period.as_str();
+
+                format!("Unusual KPLC Bill (#{account_ref}): {billing_period}")
+            }
+            AlertKind::EstimatedReading(estimated_reading) => {
+                let serial_num = estimated_reading.serial_num.as_str();
+
+                format!("KPLC Meter Reading (#{account_ref}): {serial_num}")
+            }
+            AlertKind::BillDiscrepancy(discrepancy) => {
+                let billing_period = discrepancy.billing_period.as_str();
+
+                format!("Incorrect KPLC Bill? (#{account_ref}): {billing_period}")
+            }
+            AlertKind::OverBudget(overrun) if overrun.projected => {
+                format!("KPLC Bill Projected Over Budget (#{account_ref})")
+            }
+            AlertKind::OverBudget(_) => format!("KPLC Bill Over Budget (#{account_ref})"),
+            AlertKind::LowCredit(_) => format!("KPLC Credit Running Low (#{account_ref})"),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        self.render(|text| text.to_string())
+    }
+
+    /// The message with its key figures in bold, for channels that accept HTML.
+    pub fn html_message(&self) -> String {
+        self.render(|text| format!("<b>{text}</b>"))
+    }
+
+    /// The message with its key figures in bold, for channels that accept Markdown.
+    pub fn markdown_message(&self) -> String {
+        self.render(|text| format!("**{text}**"))
+    }
+
+    /// The bill's key figures as label and value pairs, for channels that lay
+    /// them out alongside the message.
+    pub fn facts(&self) -> Vec<(&'static str, String)> {
+        let mut facts = vec![
+            ("Account", self.bill.data.account_reference.clone()),
+            ("Balance", Balance::of(self.bill.data.balance).to_string()),
+        ];
+
+        // the oldest unpaid bill is the first to fall due
+        if let Some(unpaid_bill) = self.bill.unpaid_bills().last() {
+            if self.bill.data.balance.is_sign_negative() {
+                facts.push((
+                    "Due date",
+                    unpaid_bill.due_date.format("%d %B, %Y").to_string(),
+                ));
+            }
+        }
+        if let Some(latest_bill) = self.bill.data.col_bills.first() {
+            facts.push(("Latest bill", latest_bill.billing_period.clone()));
+            facts.push(("Bill amount", format!("KES {}", latest_bill.bill_amount)));
+        }
+
+        facts
+    }
+
+    fn render(&self, emphasize: impl Fn(&str) -> String) -> String {
+        let mut lines = vec![self.render_kind(emphasize)];
+        lines.extend(self.notes.iter().cloned());
+
+        lines.join("\n")
+    }
+
+    fn render_kind(&self, emphasize: impl Fn(&str) -> String) -> String {
+        match &self.kind {
+            AlertKind::BalanceDue => match Balance::of(self.bill.data.balance) {
+                Balance::Arrears(amount) => {
+                    let balance = emphasize(&format!("KES {amount}"));
+                    let unpaid_bills = self.bill.unpaid_bills();
+
+                    match unpaid_bills.as_slice() {
+                        [] => format!("Balance of {balance} is due!"),
+                        [unpaid_bill] => {
+                            let due_date = unpaid_bill.due_date.format("%d %B, %Y");
+                            let due_date = emphasize(&due_date.to_string());
+
+                            format!("Balance of {balance} is due on {due_date}!")
+                        }
+                        _ => {
+                            let count = unpaid_bills.len();
+                            let mut lines = vec![format!(
+                                "Balance of {balance} is due on {count} unpaid bills:"
+                            )];
+                            // oldest first, as that's the order they fall due in
+                            lines.extend(unpaid_bills.iter().rev().map(|unpaid_bill| {
+                                let bill_number = unpaid_bill.bill_number.as_str();
+                                let billing_period = unpaid_bill.billing_period.as_str();
+                                let pend_amount = unpaid_bill.bill_pend_amount;
+                                let due_date = unpaid_bill.due_date.format("%d %B, %Y");
+                                let due_date = emphasize(&due_date.to_string());
+
+                                format!(
+                                    "- Bill {bill_number} ({billing_period}): KES {pend_amount} \
+                                     due on {due_date}"
+                                )
+                            }));
+
+                            lines.join("\n")
+                        }
+                    }
+                }
+                Balance::Credit(amount) => {
+                    let credit = emphasize(&format!("KES {amount}"));
+
+                    format!("Credit of {credit} on the account, nothing is due.")
+                }
+                Balance::Settled => "No balance due.".to_string(),
+            },
+            AlertKind::PaymentReceived(payment) => {
+                let amount = emphasize(&format!("KES {}", payment.amount));
+                let detected_at = payment.detected_at.format("%d %B, %Y");
+
+                format!("Payment of {amount} received by KPLC, as of {detected_at}.")
+            }
+            AlertKind::UnusualBill(anomaly) => {
+                let amount = emphasize(&format!("KES {}", anomaly.amount));
+                let percent = emphasize(&format!("{:.0}%", anomaly.percent_above_average));
+                let expected_amount = anomaly.expected_amount;
+                let bill_number = anomaly.bill_number.as_str();
+
+                format!(
+                    "Bill {bill_number} of {amount} is {percent} higher than usual \
+                     (about KES {expected_amount} for a billing period this long). \
+                     Check the meter reading before paying!"
+                )
+            }
+            AlertKind::EstimatedReading(estimated_reading) => {
+                let serial_num = emphasize(&estimated_reading.serial_num);
+                let reading_value = estimated_reading.reading_value;
+                let reading_date = estimated_reading.reading_date.format("%d %B, %Y");
+                let advice = "Submit a self-read to avoid being billed on an estimate.";
+
+                match estimated_reading.reason {
+                    EstimateReason::Estimated => format!(
+                        "The latest reading of meter {serial_num} ({reading_value} on \
+                         {reading_date}) was estimated. {advice}"
+                    ),
+                    EstimateReason::Unchanged(readings) => format!(
+                        "Meter {serial_num} has read {reading_value} for the last {readings} \
+                         readings. {advice}"
+                    ),
+                }
+            }
+            AlertKind::BillDiscrepancy(discrepancy) => {
+                let bill_number = discrepancy.bill_number.as_str();
+                let amount = emphasize(&format!("KES {}", discrepancy.bill_amount));
+                let expected_amount = emphasize(&format!("KES {}", discrepancy.expected_amount));
+                let kwh = discrepancy.kwh;
+                let difference = discrepancy.difference_percent();
+                let effective_from = discrepancy.tariff_effective_from.format("%d %B, %Y");
+
+                format!(
+                    "Bill {bill_number} of {amount} for {kwh} kWh is {difference:+.0}% off \
+                     the {expected_amount} expected under the tariff effective from \
+                     {effective_from}. Consider querying it with KPLC."
+                )
+            }
+            AlertKind::OverBudget(overrun) => {
+                let amount = emphasize(&format!("KES {}", overrun.amount));
+                let budget = overrun.budget;
+
+                if overrun.projected {
+                    format!(
+                        "At the current rate of consumption the next bill is on track for \
+                         {amount}, over the budget of KES {budget}."
+                    )
+                } else {
+                    let bill_number = overrun.bill_number.as_str();
+
+                    format!("Bill {bill_number} of {amount} is over the budget of KES {budget}.")
+                }
+            }
+            AlertKind::LowCredit(shortfall) => {
+                let credit = emphasize(&format!("KES {}", shortfall.credit));
+                let projected_amount = shortfall.projected_amount;
+                let top_up = emphasize(&format!("KES {}", shortfall.shortfall()));
+
+                format!(
+                    "Credit of {credit} won't cover the next bill, projected at KES \
+                     {projected_amount}. Top up {top_up} to stay ahead."
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::{Alert, AlertKind, Severity};
+    use crate::{
+        anomaly::Anomaly,
+        budget::BudgetOverrun,
+        credit::CreditShortfall,
+        estimate::{EstimateReason, EstimatedReading},
+        payment::Payment,
+        tariff::Discrepancy,
+        test_utils::get_kplc_bill_resp,
+    };
+
+    #[test]
+    fn test_balance_due_alert() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let alert = Alert::new(AlertKind::BalanceDue, &bill);
+
+        assert_eq!(alert.severity(), Severity::Critical);
+        assert_eq!(alert.title(), "KPLC Bill (#1234567): 10 - October 2022");
+        assert_eq!(
+            alert.message(),
+            "Balance of KES 3592.34 is due on 25 October, 2022!"
+        );
+        assert_eq!(
+            alert.html_message(),
+            "Balance of <b>KES 3592.34</b> is due on <b>25 October, 2022</b>!"
+        );
+        assert_eq!(
+            alert.markdown_message(),
+            "Balance of **KES 3592.34** is due on **25 October, 2022**!"
+        );
+        assert_eq!(
+            alert.facts(),
+            vec![
+                ("Account", "1234567".to_string()),
+                ("Balance", "KES 3592.34 in arrears".to_string()),
+                ("Due date", "25 October, 2022".to_string()),
+                ("Latest bill", "10 - October 2022".to_string()),
+                ("Bill amount", "KES 3593".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_balance_due_alert_multiple_unpaid_bills() {
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        bill.data.balance = Decimal::new(-719234, 2);
+        bill.data.col_bills[1].bill_pend_amount = Decimal::new(3600, 0);
+        let alert = Alert::new(AlertKind::BalanceDue, &bill);
+
+        assert_eq!(alert.title(), "KPLC Bill (#1234567): 10 - October 2022");
+        assert_eq!(
+            alert.message(),
+            "Balance of KES 7192.34 is due on 2 unpaid bills:\n\
+             - Bill 12516346452352 (9 - September 2022): KES 3600 due on 27 September, 2022\n\
+             - Bill 981239123213 (10 - October 2022): KES 3592.34 due on 25 October, 2022"
+        );
+    }
+
+    #[test]
+    fn test_balance_due_alert_without_bills() {
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        bill.data.col_bills.clear();
+        bill.data.meter_list.clear();
+        let alert = Alert::new(AlertKind::BalanceDue, &bill);
+
+        assert_eq!(alert.severity(), Severity::Normal);
+        assert_eq!(alert.title(), "KPLC Bill (#1234567)");
+        assert_eq!(alert.message(), "Balance of KES 3592.34 is due!");
+    }
+
+    #[test]
+    fn test_balance_alert_with_credit() {
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        bill.data.balance = Decimal::new(40766, 2);
+        let alert = Alert::new(AlertKind::BalanceDue, &bill);
+
+        assert_eq!(alert.severity(), Severity::Normal);
+        assert_eq!(
+            alert.message(),
+            "Credit of KES 407.66 on the account, nothing is due."
+        );
+    }
+
+    #[test]
+    fn test_low_credit_alert() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let shortfall = CreditShortfall {
+            bill_number: "981239123213".to_string(),
+            credit: Decimal::new(500, 0),
+            projected_amount: Decimal::new(3000, 0),
+        };
+        let alert = Alert::new(AlertKind::LowCredit(shortfall), &bill);
+
+        assert_eq!(alert.severity(), Severity::Normal);
+        assert_eq!(alert.title(), "KPLC Credit Running Low (#1234567)");
+        assert_eq!(
+            alert.message(),
+            "Credit of KES 500 won't cover the next bill, projected at KES 3000. \
+             Top up KES 2500 to stay ahead."
+        );
+    }
+
+    #[test]
+    fn test_alert_with_notes() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let alert = Alert::new(AlertKind::BalanceDue, &bill).with_notes(vec![
+            "Used 523 kWh over 28 days (18.7 kWh/day), about KES 6.87/kWh.".to_string(),
+        ]);
+
+        assert_eq!(
+            alert.message(),
+            "Balance of KES 3592.34 is due on 25 October, 2022!\n\
+             Used 523 kWh over 28 days (18.7 kWh/day), about KES 6.87/kWh."
+        );
+    }
+
+    #[test]
+    fn test_payment_received_alert() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let payment = Payment {
+            amount: Decimal::new(359234, 2),
+            detected_at: Utc.with_ymd_and_hms(2022, 10, 20, 6, 0, 0).unwrap(),
+        };
+        let alert = Alert::new(AlertKind::PaymentReceived(payment), &bill);
+
+        assert_eq!(alert.severity(), Severity::Low);
+        assert_eq!(alert.title(), "KPLC Payment Received (#1234567)");
+        assert_eq!(
+            alert.message(),
+            "Payment of KES 3592.34 received by KPLC, as of 20 October, 2022."
+        );
+    }
+
+    #[test]
+    fn test_unusual_bill_alert() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let anomaly = Anomaly {
+            bill_number: "981239123213".to_string(),
+            billing_period: "10 - October 2022".to_string(),
+            amount: Decimal::new(10779, 0),
+            expected_amount: Decimal::new(359017, 2),
+            percent_above_average: 200.23,
+            z_score: None,
+        };
+        let alert = Alert::new(AlertKind::UnusualBill(anomaly), &bill);
+
+        assert_eq!(alert.severity(), Severity::High);
+        assert_eq!(
+            alert.title(),
+            "Unusual KPLC Bill (#1234567): 10 - October 2022"
+        );
+        assert_eq!(
+            alert.message(),
+            "Bill 981239123213 of KES 10779 is 200% higher than usual \
+             (about KES 3590.17 for a billing period this long). \
+             Check the meter reading before paying!"
+        );
+    }
+
+    #[test]
+    fn test_estimated_reading_alert() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let estimated_reading = EstimatedReading {
+            serial_num: "981928391283".to_string(),
+            reading_date: Utc.with_ymd_and_hms(2022, 10, 4, 21, 0, 0).unwrap(),
+            reading_value: 18234,
+            reason: EstimateReason::Estimated,
+        };
+        let alert = Alert::new(AlertKind::EstimatedReading(estimated_reading), &bill);
+
+        assert_eq!(alert.severity(), Severity::Normal);
+        assert_eq!(alert.title(), "KPLC Meter Reading (#1234567): 981928391283");
+        assert_eq!(
+            alert.message(),
+            "The latest reading of meter 981928391283 (18234 on 04 October, 2022) \
+             was estimated. Submit a self-read to avoid being billed on an estimate."
+        );
+    }
+
+    #[test]
+    fn test_bill_discrepancy_alert() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let discrepancy = Discrepancy {
+            bill_number: "981239123213".to_string(),
+            billing_period: "10 - October 2022".to_string(),
+            kwh: 150,
+            bill_amount: Decimal::new(7500, 0),
+            expected_amount: Decimal::new(494510, 2),
+            tariff_effective_from: NaiveDate::from_ymd_opt(2018, 11, 1).unwrap(),
+        };
+        let alert = Alert::new(AlertKind::BillDiscrepancy(discrepancy), &bill);
+
+        assert_eq!(alert.severity(), Severity::High);
+        assert_eq!(
+            alert.title(),
+            "Incorrect KPLC Bill? (#1234567): 10 - October 2022"
+        );
+        assert_eq!(
+            alert.message(),
+            "Bill 981239123213 of KES 7500 for 150 kWh is +52% off the KES 4945.10 \
+             expected under the tariff effective from 01 November, 2018. \
+             Consider querying it with KPLC."
+        );
+    }
+
+    #[test]
+    fn test_over_budget_alert() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let mut overrun = BudgetOverrun {
+            bill_number: "981239123213".to_string(),
+            budget: Decimal::new(3000, 0),
+            amount: Decimal::new(359234, 2),
+            projected: false,
+        };
+        let alert = Alert::new(AlertKind::OverBudget(overrun.clone()), &bill);
+
+        assert_eq!(alert.severity(), Severity::High);
+        assert_eq!(alert.title(), "KPLC Bill Over Budget (#1234567)");
+        assert_eq!(
+            alert.message(),
+            "Bill 981239123213 of KES 3592.34 is over the budget of KES 3000."
+        );
+
+        overrun.amount = Decimal::new(3800, 0);
+        overrun.projected = true;
+        let alert = Alert::new(AlertKind::OverBudget(overrun), &bill);
+
+        assert_eq!(alert.title(), "KPLC Bill Projected Over Budget (#1234567)");
+        assert_eq!(
+            alert.message(),
+            "At the current rate of consumption the next bill is on track for \
+             KES 3800, over the budget of KES 3000."
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::Deserialize;
+
+use crate::state::{AccountState, BillRecord};
+
+// fewest earlier bills needed before the latest one is judged against them
+const MIN_HISTORY: usize = 3;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AnomalySettings {
+    /// Number of earlier bills making up the trailing average.
+    #[serde(default = "default_window")]
+    pub window: usize,
+    /// Flag bills this many percent above the trailing average.
+    pub percent_above_average: Option<f64>,
+    /// Flag bills this many standard deviations above the trailing average.
+    pub z_score: Option<f64>,
+}
+
+fn default_window() -> usize {
+    6
+}
+
+/// A bill that's unusually high compared to the ones before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    pub bill_number: String,
+    pub billing_period: String,
+    pub amount: Decimal,
+    /// What the bill would have been at the trailing average daily cost.
+    pub expected_amount: Decimal,
+    pub percent_above_average: f64,
+    pub z_score: Option<f64>,
+}
+
+// bills cover billing periods of varying length, so they're compared by
+// their cost per day
+fn daily_amount(bill: &BillRecord) -> f64 {
+    let days = (bill.to_date - bill.from_date).num_days().max(1);
+
+    bill.bill_amount.to_f64().unwrap_or_default() / days as f64
+}
+
+/// Checks whether the latest bill is unusually high compared to the trailing
+/// average of the bills before it, going by every bill seen on the account
+/// rather than just the few in the latest fetch.
+pub fn detect(account_state: &AccountState, settings: &AnomalySettings) -> Option<Anomaly> {
+    // bills are kept newest first
+    let (latest, earlier) = account_state.bills.split_first()?;
+    let history: Vec<f64> = earlier
+        .iter()
+        .take(settings.window)
+        .map(daily_amount)
+        .collect();
+
+    if history.len() < MIN_HISTORY {
+        return None;
+    }
+
+    let mean = history.iter().sum::<f64>() / history.len() as f64;
+    if mean <= 0.0 {
+        return None;
+    }
+    let variance = history.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / history.len() as f64;
+    let std_dev = variance.sqrt();
+
+    let latest_daily = daily_amount(latest);
+    let percent_above_average = (latest_daily - mean) / mean * 100.0;
+    let z_score = if std_dev > 0.0 {
+        Some((latest_daily - mean) / std_dev)
+    } else {
+        None
+    };
+
+    let above_percentage = settings
+        .percent_above_average
+        .is_some_and(|threshold| percent_above_average >= threshold);
+    let above_z_score = match (settings.z_score, z_score) {
+        (Some(threshold), Some(z_score)) => z_score >= threshold,
+        _ => false,
+    };
+
+    if !above_percentage && !above_z_score {
+        return None;
+    }
+
+    let days = (latest.to_date - latest.from_date).num_days().max(1);
+    let expected_amount = Decimal::from_f64_retain(mean * days as f64)
+        .unwrap_or_default()
+        .round_dp(2);
+
+    Some(Anomaly {
+        bill_number: latest.bill_number.clone(),
+        billing_period: latest.billing_period.clone(),
+        amount: latest.bill_amount,
+        expected_amount,
+        percent_above_average,
+        z_score,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::{detect, AnomalySettings};
+    use crate::{kplc::KPLCBill, state::AccountState, test_utils::get_kplc_bill_resp};
+
+    fn make_settings() -> AnomalySettings {
+        AnomalySettings {
+            window: 6,
+            percent_above_average: Some(50.0),
+            z_score: None,
+        }
+    }
+
+    fn make_account_state(bill: &KPLCBill) -> AccountState {
+        let mut account_state = AccountState::default();
+        account_state.record(bill);
+
+        account_state
+    }
+
+    #[test]
+    fn test_detect_usual_bill() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        assert_eq!(detect(&make_account_state(&bill), &make_settings()), None);
+    }
+
+    #[test]
+    fn test_detect_unusual_bill() {
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        bill.data.col_bills[0].bill_amount = Decimal::new(10779, 0);
+
+        let anomaly = detect(&make_account_state(&bill), &make_settings()).unwrap();
+        assert_eq!(anomaly.bill_number, "981239123213");
+        assert_eq!(anomaly.billing_period, "10 - October 2022");
+        assert_eq!(anomaly.amount, Decimal::new(10779, 0));
+        assert!(anomaly.expected_amount > Decimal::new(3000, 0));
+        assert!(anomaly.percent_above_average > 150.0);
+    }
+
+    #[test]
+    fn test_detect_unusual_bill_by_z_score() {
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        bill.data.col_bills[0].bill_amount = Decimal::new(10779, 0);
+        let settings = AnomalySettings {
+            percent_above_average: None,
+            z_score: Some(3.0),
+            ..make_settings()
+        };
+
+        let anomaly = detect(&make_account_state(&bill), &settings).unwrap();
+        assert!(anomaly.z_score.unwrap() >= 3.0);
+    }
+
+    #[test]
+    fn test_detect_without_enough_history() {
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        bill.data.col_bills.truncate(3);
+        bill.data.col_bills[0].bill_amount = Decimal::new(10779, 0);
+
+        assert_eq!(detect(&make_account_state(&bill), &make_settings()), None);
+    }
+
+    #[test]
+    fn test_detect_with_history_from_earlier_fetches() {
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let mut account_state = make_account_state(&bill);
+
+        // a later fetch only returning the latest bill
+        bill.data.col_bills.truncate(1);
+        bill.data.col_bills[0].bill_amount = Decimal::new(10779, 0);
+        account_state.record(&bill);
+
+        let anomaly = detect(&account_state, &make_settings()).unwrap();
+        assert_eq!(anomaly.bill_number, "981239123213");
+        assert_eq!(anomaly.amount, Decimal::new(10779, 0));
+    }
+}
@@ -0,0 +1,263 @@
+use rust_decimal::Decimal;
+
+use crate::state::{AccountState, BillRecord};
+
+// number of most recent bills the projection follows the consumption trend of
+const TREND_PERIODS: usize = 3;
+
+/// A bill, actual or projected, that exceeds the account's budget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetOverrun {
+    pub bill_number: String,
+    pub budget: Decimal,
+    pub amount: Decimal,
+    pub projected: bool,
+}
+
+/// Projects the next bill from the recent consumption trend at the latest
+/// cost per kWh, falling back to the recent daily cost of bills when there
+/// isn't enough meter reading history.
+pub fn project(account_state: &AccountState) -> Option<Decimal> {
+    let latest_bill = account_state.bills.first()?;
+    let cycle_days = (latest_bill.to_date - latest_bill.from_date)
+        .num_days()
+        .max(1);
+
+    // an account can have several meters, each with its own consumption
+    // period towards the same bill, so the trend goes by bill
+    let recent_bills: Vec<(&BillRecord, usize, i64)> = account_state
+        .bills
+        .iter()
+        .filter_map(|bill| {
+            let periods: Vec<_> = account_state
+                .consumption
+                .iter()
+                .filter(|period| period.bill_number.as_ref() == Some(&bill.bill_number))
+                .collect();
+            let kwh = periods.iter().map(|period| period.kwh).sum();
+            let days = periods.iter().map(|period| period.days()).max()?;
+
+            Some((bill, kwh, days))
+        })
+        .take(TREND_PERIODS)
+        .collect();
+    let kwh: usize = recent_bills.iter().map(|(_, kwh, _)| kwh).sum();
+    let days: i64 = recent_bills.iter().map(|(_, _, days)| days).sum();
+    let cost_per_kwh = recent_bills
+        .first()
+        .filter(|(_, kwh, _)| *kwh > 0)
+        .map(|(bill, kwh, _)| bill.bill_amount / Decimal::from(*kwh));
+
+    let daily_amount = match cost_per_kwh {
+        Some(cost_per_kwh) if days > 0 => Decimal::from(kwh) / Decimal::from(days) * cost_per_kwh,
+        _ => {
+            let recent_bills = &account_state.bills[..account_state.bills.len().min(TREND_PERIODS)];
+            let days: i64 = recent_bills
+                .iter()
+                .map(|bill| (bill.to_date - bill.from_date).num_days().max(1))
+                .sum();
+            let amount: Decimal = recent_bills.iter().map(|bill| bill.bill_amount).sum();
+
+            amount / Decimal::from(days)
+        }
+    };
+
+    Some((daily_amount * Decimal::from(cycle_days)).round())
+}
+
+/// Checks the latest bill, then the projected one, against the budget.
+pub fn check(account_state: &AccountState, budget: Decimal) -> Option<BudgetOverrun> {
+    let latest_bill = account_state.bills.first()?;
+
+    if latest_bill.bill_amount > budget {
+        return Some(BudgetOverrun {
+            bill_number: latest_bill.bill_number.clone(),
+            budget,
+            amount: latest_bill.bill_amount,
+            projected: false,
+        });
+    }
+
+    match project(account_state) {
+        Some(projection) if projection > budget => Some(BudgetOverrun {
+            bill_number: latest_bill.bill_number.clone(),
+            budget,
+            amount: projection,
+            projected: true,
+        }),
+        _ => None,
+    }
+}
+
+/// One line summary of the projection, as included in alerts.
+pub fn projection_summary(account_state: &AccountState, budget: Option<Decimal>) -> Option<String> {
+    let projection = project(account_state)?;
+
+    Some(match budget {
+        Some(budget) => format!("On track for KES {projection} this cycle (budget KES {budget})."),
+        None => format!("On track for KES {projection} this cycle."),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::{check, project, projection_summary, BudgetOverrun};
+    use crate::{
+        consumption,
+        state::{AccountState, BillRecord, MeterReading},
+    };
+
+    fn make_bill(bill_number: &str, month: u32, amount: i64) -> BillRecord {
+        BillRecord {
+            bill_number: bill_number.to_string(),
+            billing_period: format!("{month} - 2022"),
+            from_date: Utc.with_ymd_and_hms(2022, month - 1, 4, 21, 0, 0).unwrap(),
+            to_date: Utc.with_ymd_and_hms(2022, month, 4, 21, 0, 0).unwrap(),
+            due_date: Utc.with_ymd_and_hms(2022, month, 25, 21, 0, 0).unwrap(),
+            bill_amount: Decimal::new(amount, 0),
+            bill_pend_amount: Decimal::ZERO,
+        }
+    }
+
+    fn make_reading(month: u32, value: usize) -> MeterReading {
+        MeterReading {
+            serial_num: "981928391283".to_string(),
+            reading_date: Utc.with_ymd_and_hms(2022, month, 4, 21, 0, 0).unwrap(),
+            reading_value: value,
+            estimated: false,
+        }
+    }
+
+    fn make_account_state() -> AccountState {
+        let mut account_state = AccountState {
+            // September and October bills cover 31 and 30 days
+            bills: vec![make_bill("2", 10, 3000), make_bill("1", 9, 3100)],
+            readings: vec![make_reading(10, 1300), make_reading(9, 1000)],
+            ..Default::default()
+        };
+        account_state.consumption =
+            consumption::compute(&account_state.readings, &account_state.bills);
+
+        account_state
+    }
+
+    #[test]
+    fn test_project_from_consumption() {
+        // 10 kWh a day at KES 10/kWh over a 30 day cycle
+        assert_eq!(project(&make_account_state()), Some(Decimal::new(3000, 0)));
+    }
+
+    #[test]
+    fn test_project_from_recent_consumption() {
+        // usage, and the cost per kWh, halved over the last three cycles
+        let mut account_state = AccountState {
+            bills: vec![
+                make_bill("5", 10, 3000),
+                make_bill("4", 9, 3000),
+                make_bill("3", 8, 3000),
+                make_bill("2", 7, 12000),
+                make_bill("1", 6, 12000),
+            ],
+            readings: vec![
+                make_reading(10, 3100),
+                make_reading(9, 2800),
+                make_reading(8, 2500),
+                make_reading(7, 2200),
+                make_reading(6, 1600),
+                make_reading(5, 1000),
+            ],
+            ..Default::default()
+        };
+        account_state.consumption =
+            consumption::compute(&account_state.readings, &account_state.bills);
+
+        // 900 kWh over 92 days at KES 10/kWh over a 30 day cycle
+        assert_eq!(project(&account_state), Some(Decimal::new(2935, 0)));
+    }
+
+    #[test]
+    fn test_project_from_several_meters() {
+        let mut account_state = AccountState {
+            bills: vec![make_bill("2", 10, 3000), make_bill("1", 9, 3100)],
+            readings: vec![
+                make_reading(10, 1410),
+                make_reading(9, 1210),
+                make_reading(8, 1000),
+                MeterReading {
+                    serial_num: "981928391284".to_string(),
+                    ..make_reading(10, 700)
+                },
+                MeterReading {
+                    serial_num: "981928391284".to_string(),
+                    ..make_reading(9, 600)
+                },
+                MeterReading {
+                    serial_num: "981928391284".to_string(),
+                    ..make_reading(8, 500)
+                },
+            ],
+            ..Default::default()
+        };
+        account_state.consumption =
+            consumption::compute(&account_state.readings, &account_state.bills);
+
+        // 610 kWh across both meters over 61 days at KES 10/kWh over a 30 day cycle
+        assert_eq!(project(&account_state), Some(Decimal::new(3000, 0)));
+    }
+
+    #[test]
+    fn test_project_from_bills() {
+        let account_state = AccountState {
+            bills: vec![make_bill("2", 10, 3000), make_bill("1", 9, 3100)],
+            ..Default::default()
+        };
+
+        // KES 6100 over 61 days
+        assert_eq!(project(&account_state), Some(Decimal::new(3000, 0)));
+        assert_eq!(project(&AccountState::default()), None);
+    }
+
+    #[test]
+    fn test_check_budget() {
+        let account_state = make_account_state();
+
+        assert_eq!(check(&account_state, Decimal::new(3500, 0)), None);
+        assert_eq!(
+            check(&account_state, Decimal::new(2500, 0)),
+            Some(BudgetOverrun {
+                bill_number: "2".to_string(),
+                budget: Decimal::new(2500, 0),
+                amount: Decimal::new(3000, 0),
+                projected: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_check_projected_budget() {
+        let mut account_state = make_account_state();
+        // heavy usage the cycle before the latest
+        account_state.readings.push(make_reading(8, 0));
+        account_state.consumption =
+            consumption::compute(&account_state.readings, &account_state.bills);
+
+        // 1300 kWh over 61 days at KES 10/kWh over a 30 day cycle
+        let overrun = check(&account_state, Decimal::new(3500, 0)).unwrap();
+        assert!(overrun.projected);
+        assert_eq!(overrun.amount, Decimal::new(6393, 0));
+    }
+
+    #[test]
+    fn test_projection_summary() {
+        let account_state = make_account_state();
+
+        assert_eq!(
+            projection_summary(&account_state, Some(Decimal::new(4800, 0))).unwrap(),
+            "On track for KES 3000 this cycle (budget KES 4800)."
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use chrono::{prelude::DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::state::BillRecord;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CalendarSettings {
+    /// Days before each due date to be reminded on.
+    #[serde(default = "default_reminder_days")]
+    pub reminder_days: Vec<u32>,
+}
+
+impl Default for CalendarSettings {
+    fn default() -> Self {
+        CalendarSettings {
+            reminder_days: default_reminder_days(),
+        }
+    }
+}
+
+fn default_reminder_days() -> Vec<u32> {
+    vec![3, 1]
+}
+
+// longest a content line may be, in octets, before it's folded
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Renders an iCalendar with an all-day event on the due date of every bill.
+pub fn render(
+    account_number: &str,
+    bills: &[BillRecord],
+    settings: &CalendarSettings,
+    now: DateTime<Utc>,
+) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//kplc-bill-alert//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:KPLC bills (#{account_number})"),
+    ];
+
+    // oldest first
+    for bill in bills.iter().rev() {
+        let due_date = bill.due_date.date_naive();
+        let summary = format!(
+            "KPLC bill due: KES {} (#{account_number})",
+            bill.bill_amount
+        );
+        let description = format!(
+            "Bill {} for {} of KES {}, with KES {} pending.",
+            bill.bill_number, bill.billing_period, bill.bill_amount, bill.bill_pend_amount
+        );
+
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}-{account_number}@kplc-bill-alert", bill.bill_number),
+            format!("DTSTAMP:{}", now.format("%Y%m%dT%H%M%SZ")),
+            format!("DTSTART;VALUE=DATE:{}", due_date.format("%Y%m%d")),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                (due_date + Duration::days(1)).format("%Y%m%d")
+            ),
+            format!("SUMMARY:{}", escape(&summary)),
+            format!("DESCRIPTION:{}", escape(&description)),
+        ]);
+        for days in settings.reminder_days.iter() {
+            lines.extend([
+                "BEGIN:VALARM".to_string(),
+                "ACTION:DISPLAY".to_string(),
+                format!("TRIGGER:-P{days}D"),
+                format!("DESCRIPTION:{}", escape(&summary)),
+                "END:VALARM".to_string(),
+            ]);
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line longer than `MAX_LINE_OCTETS`, continuing it on
+/// lines starting with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut octets = 0;
+
+    for c in line.chars() {
+        let limit = if folded.len() == octets {
+            MAX_LINE_OCTETS
+        } else {
+            // continuation lines lose an octet to the leading space
+            MAX_LINE_OCTETS - 1
+        };
+        if octets + c.len_utf8() > limit {
+            folded.push_str("\r\n ");
+            octets = 0;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::{fold, render, CalendarSettings};
+    use crate::state::BillRecord;
+
+    #[test]
+    fn test_render_calendar() {
+        let bills = vec![BillRecord {
+            bill_number: "981239123213".to_string(),
+            billing_period: "10 - October 2022".to_string(),
+            from_date: Utc.with_ymd_and_hms(2022, 9, 7, 21, 0, 0).unwrap(),
+            to_date: Utc.with_ymd_and_hms(2022, 10, 4, 21, 0, 0).unwrap(),
+            due_date: Utc.with_ymd_and_hms(2022, 10, 25, 21, 0, 0).unwrap(),
+            bill_amount: Decimal::new(3593, 0),
+            bill_pend_amount: Decimal::new(359234, 2),
+        }];
+        let settings = CalendarSettings {
+            reminder_days: vec![3],
+        };
+        let now = Utc.with_ymd_and_hms(2022, 10, 20, 6, 0, 0).unwrap();
+
+        assert_eq!(
+            render("1234567", &bills, &settings, now),
+            "BEGIN:VCALENDAR\r\n\
+             VERSION:2.0\r\n\
+             PRODID:-//kplc-bill-alert//EN\r\n\
+             CALSCALE:GREGORIAN\r\n\
+             X-WR-CALNAME:KPLC bills (#1234567)\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:981239123213-1234567@kplc-bill-alert\r\n\
+             DTSTAMP:20221020T060000Z\r\n\
+             DTSTART;VALUE=DATE:20221025\r\n\
+             DTEND;VALUE=DATE:20221026\r\n\
+             SUMMARY:KPLC bill due: KES 3593 (#1234567)\r\n\
+             DESCRIPTION:Bill 981239123213 for 10 - October 2022 of KES 3593\\, with KES \r\n\
+             \x203592.34 pending.\r\n\
+             BEGIN:VALARM\r\n\
+             ACTION:DISPLAY\r\n\
+             TRIGGER:-P3D\r\n\
+             DESCRIPTION:KPLC bill due: KES 3593 (#1234567)\r\n\
+             END:VALARM\r\n\
+             END:VEVENT\r\n\
+             END:VCALENDAR\r\n"
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        let line = "x".repeat(160);
+        let folded = fold(&line);
+
+        let lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 75);
+        assert_eq!(lines[1].len(), 75);
+        assert_eq!(lines[2], format!(" {}", "x".repeat(11)));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{alert::Alert, settings::Settings};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use serde::Deserialize;
+
+use super::Channel;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct EmailSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    pub smtp_host: String,
+    /// Defaults to 587 with STARTTLS, 465 with TLS and 25 without either.
+    pub smtp_port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Address the alerts are sent from, e.g. `KPLC Alerts <alerts@example.com>`.
+    pub from: String,
+    /// Addresses the alerts are sent to.
+    pub to: Vec<String>,
+}
+
+/// How the connection to the SMTP server is secured.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    #[default]
+    Starttls,
+    Tls,
+    /// Plain SMTP, for relays on the local network.
+    None,
+}
+
+/// Sends alerts as emails through an SMTP server.
+pub struct Email {
+    settings: EmailSettings,
+}
+
+impl Email {
+    pub fn with_settings(settings: EmailSettings) -> Email {
+        Email { settings }
+    }
+
+    fn message(&self, alert: &Alert<'_>) -> Result<Message> {
+        let mut builder = Message::builder()
+            .from(self.settings.from.parse::<Mailbox>()?)
+            .subject(alert.title());
+        for to in self.settings.to.iter() {
+            builder = builder.to(to.parse::<Mailbox>()?);
+        }
+
+        Ok(builder.multipart(MultiPart::alternative_plain_html(
+            alert.message(),
+            alert.html_message().replace('\n', "<br>"),
+        ))?)
+    }
+
+    fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+        let host = self.settings.smtp_host.as_str();
+        let mut builder = match self.settings.security {
+            SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        };
+        if let Some(port) = self.settings.smtp_port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = &self.settings.username {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                self.settings.password.clone().unwrap_or_default(),
+            ));
+        }
+
+        Ok(builder.build())
+    }
+}
+
+#[async_trait]
+impl Channel for Email {
+    fn new(settings: &Settings) -> Email {
+        Email::with_settings(settings.email.clone())
+    }
+
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let message = self.message(alert).context("failed building alert email")?;
+
+        self.transport()?
+            .send(message)
+            .await
+            .context("failed sending alert email")?;
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use pretty_assertions::assert_eq;
+
+    use super::{Email, EmailSettings, SmtpSecurity};
+
+    fn make_email(smtp_port: u16) -> Email {
+        Email::with_settings(EmailSettings {
+            enabled: true,
+            smtp_host: "127.0.0.1".to_string(),
+            smtp_port: Some(smtp_port),
+            security: SmtpSecurity::None,
+            from: "KPLC Alerts <alerts@example.com>".to_string(),
+            to: vec!["jane@example.com".to_string()],
+            ..Default::default()
+        })
+    }
+
+    /// Accepts a single SMTP session, returning the commands and data it was sent.
+    async fn fake_smtp_server() -> (u16, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut received = vec![];
+            let mut in_data = false;
+
+            writer.write_all(b"220 localhost\r\n").await.unwrap();
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let reply: &[u8] = if in_data {
+                    if line != "." {
+                        received.push(line);
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line == "DATA" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT" {
+                    writer.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    received.push(line);
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).await.unwrap();
+            }
+
+            received
+        });
+
+        (port, handle)
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_successfully() {
+        let (port, server) = fake_smtp_server().await;
+        let e = make_email(port);
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let result = e
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        assert_eq!(result.unwrap(), None);
+
+        let received = server.await.unwrap();
+        assert!(received.contains(&"MAIL FROM:<alerts@example.com>".to_string()));
+        assert!(received.contains(&"RCPT TO:<jane@example.com>".to_string()));
+        assert!(received.contains(&"Subject: KPLC Bill (#1234567): 10 - October 2022".to_string()));
+        assert!(
+            received.contains(&"Balance of KES 3592.34 is due on 25 October, 2022!".to_string())
+        );
+        assert!(received.contains(
+            &"Balance of <b>KES 3592.34</b> is due on <b>25 October, 2022</b>!".to_string()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_invalid_address() {
+        let mut e = make_email(25);
+        e.settings.to = vec!["not an address".to_string()];
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let result = e
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "failed building alert email"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::{io::ErrorKind, process::Stdio, time::Duration};
+
+use crate::{alert::Alert, settings::Settings};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use super::Channel;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExecSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Program to run, followed by its arguments.
+    #[serde(default)]
+    pub command: Vec<String>,
+    /// Seconds to wait for the command before it's killed.
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+impl Default for ExecSettings {
+    fn default() -> Self {
+        ExecSettings {
+            enabled: false,
+            command: vec![],
+            timeout_seconds: default_timeout_seconds(),
+        }
+    }
+}
+
+fn default_timeout_seconds() -> u64 {
+    30
+}
+
+/// Runs a command for every alert, with the bill in its environment and as
+/// JSON on its standard input. The alert counts as sent if it exits with `0`.
+pub struct Exec {
+    settings: ExecSettings,
+}
+
+#[async_trait]
+impl Channel for Exec {
+    fn new(settings: &Settings) -> Exec {
+        Exec {
+            settings: settings.exec.clone(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "exec"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let (program, args) = self
+            .settings
+            .command
+            .split_first()
+            .ok_or_else(|| anyhow!("no exec command configured"))?;
+        let stdin = serde_json::to_vec(alert.bill)?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .envs(environment(alert))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("error running {program}"))?;
+
+        let run = async {
+            let mut child_stdin = child.stdin.take().unwrap();
+            // the command is free to ignore its input and exit early
+            if let Err(err) = child_stdin.write_all(&stdin).await {
+                if err.kind() != ErrorKind::BrokenPipe {
+                    return Err(err);
+                }
+            }
+            drop(child_stdin);
+
+            child.wait_with_output().await
+        };
+
+        let timeout = Duration::from_secs(self.settings.timeout_seconds);
+        let output = tokio::time::timeout(timeout, run).await.map_err(|_| {
+            anyhow!(
+                "{program} timed out after {} seconds",
+                self.settings.timeout_seconds
+            )
+        })??;
+
+        if output.status.success() {
+            Ok(None)
+        } else {
+            Err(anyhow!(
+                "{program} failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+}
+
+fn environment(alert: &Alert<'_>) -> Vec<(&'static str, String)> {
+    let bill = alert.bill;
+    let latest_bill = bill.data.col_bills.first();
+    // the oldest unpaid bill is the first to fall due
+    let due_date = match bill.unpaid_bills().last() {
+        Some(unpaid_bill) if bill.data.balance.is_sign_negative() => {
+            unpaid_bill.due_date.format("%Y-%m-%d").to_string()
+        }
+        _ => String::new(),
+    };
+
+    vec![
+        ("KPLC_ACCOUNT", bill.data.account_reference.clone()),
+        ("KPLC_BALANCE", bill.data.balance.to_string()),
+        ("KPLC_DUE_DATE", due_date),
+        (
+            "KPLC_BILL_AMOUNT",
+            latest_bill
+                .map(|latest_bill| latest_bill.bill_amount.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "KPLC_BILLING_PERIOD",
+            latest_bill
+                .map(|latest_bill| latest_bill.billing_period.clone())
+                .unwrap_or_default(),
+        ),
+        ("KPLC_ALERT_TITLE", alert.title()),
+        ("KPLC_ALERT_MESSAGE", alert.message()),
+        (
+            "KPLC_ALERT_SEVERITY",
+            format!("{:?}", alert.severity()).to_lowercase(),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use pretty_assertions::assert_eq;
+
+    use super::{environment, Exec, ExecSettings};
+
+    fn make_exec(script: &str) -> Exec {
+        Exec {
+            settings: ExecSettings {
+                enabled: true,
+                command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+                timeout_seconds: 1,
+            },
+        }
+    }
+
+    #[test]
+    fn test_environment() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let alert = Alert::new(AlertKind::BalanceDue, &bill);
+
+        assert_eq!(
+            environment(&alert),
+            vec![
+                ("KPLC_ACCOUNT", "1234567".to_string()),
+                ("KPLC_BALANCE", "-3592.34".to_string()),
+                ("KPLC_DUE_DATE", "2022-10-25".to_string()),
+                ("KPLC_BILL_AMOUNT", "3593".to_string()),
+                ("KPLC_BILLING_PERIOD", "10 - October 2022".to_string()),
+                (
+                    "KPLC_ALERT_TITLE",
+                    "KPLC Bill (#1234567): 10 - October 2022".to_string()
+                ),
+                (
+                    "KPLC_ALERT_MESSAGE",
+                    "Balance of KES 3592.34 is due on 25 October, 2022!".to_string()
+                ),
+                ("KPLC_ALERT_SEVERITY", "critical".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_successfully() {
+        let e = make_exec(
+            "test \"$KPLC_ACCOUNT\" = 1234567 && grep -q '\"accountReference\":\"1234567\"'",
+        );
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let result = e
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_error() {
+        let e = make_exec("echo 'no route to host' >&2; exit 3");
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let result = e
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "sh failed with exit status: 3: no route to host"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_timeout() {
+        let e = make_exec("sleep 5");
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let result = e
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "sh timed out after 1 seconds"
+        );
+    }
+}
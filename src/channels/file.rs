@@ -0,0 +1,212 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::{alert::Alert, settings::Settings};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use tokio::{
+    fs::{self, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use super::{line, Channel};
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FileSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_path")]
+    pub path: PathBuf,
+    /// Template each alert is written with, instead of as JSON.
+    pub template: Option<String>,
+    /// Size, in bytes, past which the file is rotated.
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+    /// Number of rotated files kept, as `<path>.1` (the newest) and onwards.
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for FileSettings {
+    fn default() -> Self {
+        FileSettings {
+            enabled: false,
+            path: default_path(),
+            template: None,
+            max_bytes: default_max_bytes(),
+            max_files: default_max_files(),
+        }
+    }
+}
+
+fn default_path() -> PathBuf {
+    PathBuf::from("kplc-alerts.log")
+}
+
+fn default_max_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+/// Appends each alert as a line to a file, rotating it once it grows too big.
+pub struct File {
+    settings: FileSettings,
+}
+
+#[async_trait]
+impl Channel for File {
+    fn new(settings: &Settings) -> File {
+        File {
+            settings: settings.file.clone(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let line = line::render(alert, self.settings.template.as_deref(), Utc::now()) + "\n";
+        let path = self.settings.path.as_path();
+
+        self.rotate(path, line.len() as u64)
+            .await
+            .with_context(|| format!("error rotating {}", path.display()))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("error opening {}", path.display()))?;
+        file.write_all(line.as_bytes())
+            .await
+            .with_context(|| format!("error writing alert to {}", path.display()))?;
+        // tokio hands writes off to a blocking thread, so they only land once flushed
+        file.flush()
+            .await
+            .with_context(|| format!("error writing alert to {}", path.display()))?;
+
+        Ok(None)
+    }
+}
+
+impl File {
+    /// Shifts `<path>` to `<path>.1`, `<path>.1` to `<path>.2` and so on if
+    /// appending `len` bytes would take it past `max_bytes`, dropping the oldest.
+    async fn rotate(&self, path: &Path, len: u64) -> Result<()> {
+        let size = match fs::metadata(path).await {
+            Ok(metadata) => metadata.len(),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        if size == 0 || size + len <= self.settings.max_bytes {
+            return Ok(());
+        }
+
+        let rotated = |n: usize| PathBuf::from(format!("{}.{n}", path.display()));
+
+        if self.settings.max_files == 0 {
+            fs::remove_file(path).await?;
+            return Ok(());
+        }
+        match fs::remove_file(rotated(self.settings.max_files)).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+        for n in (1..self.settings.max_files).rev() {
+            match fs::rename(rotated(n), rotated(n + 1)).await {
+                Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+        }
+        fs::rename(path, rotated(1)).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    use super::{File, FileSettings};
+
+    #[tokio::test]
+    async fn test_send_alert_appends_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("alerts.log");
+        let f = File {
+            settings: FileSettings {
+                enabled: true,
+                path: path.clone(),
+                template: Some("[{severity}] {title}".to_string()),
+                ..Default::default()
+            },
+        };
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let alert = Alert::new(AlertKind::BalanceDue, &bill);
+
+        assert_eq!(f.send_alert(&alert).await.unwrap(), None);
+        assert_eq!(f.send_alert(&alert).await.unwrap(), None);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[critical] KPLC Bill (#1234567): 10 - October 2022\n\
+             [critical] KPLC Bill (#1234567): 10 - October 2022\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_rotates_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("alerts.log");
+        let f = File {
+            settings: FileSettings {
+                enabled: true,
+                path: path.clone(),
+                template: Some("{account}".to_string()),
+                // room for a single line
+                max_bytes: 10,
+                max_files: 2,
+            },
+        };
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let alert = Alert::new(AlertKind::BalanceDue, &bill);
+
+        for _ in 0..4 {
+            f.send_alert(&alert).await.unwrap();
+        }
+
+        let mut files: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["alerts.log", "alerts.log.1", "alerts.log.2"]);
+        for file in files {
+            assert_eq!(
+                fs::read_to_string(dir.path().join(file)).unwrap(),
+                "1234567\n"
+            );
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{alert::Alert, client, settings::Settings};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::Channel;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct GoogleChatSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// URL of the space's incoming webhook, including its `key` and `token`.
+    pub webhook_url: String,
+}
+
+/// Posts alerts to a Google Chat space as cards.
+pub struct GoogleChat {
+    settings: GoogleChatSettings,
+    http_client: Client,
+}
+
+impl GoogleChat {
+    pub fn with_settings(settings: GoogleChatSettings) -> GoogleChat {
+        let http_client = client::get_http_client().unwrap();
+
+        GoogleChat {
+            settings,
+            http_client,
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for GoogleChat {
+    fn new(settings: &Settings) -> GoogleChat {
+        GoogleChat::with_settings(settings.google_chat.clone())
+    }
+
+    fn name(&self) -> &str {
+        "Google Chat"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let resp = self
+            .http_client
+            .post(self.settings.webhook_url.as_str())
+            .json(&card(alert))
+            .send()
+            .await?;
+
+        if resp.status().is_success() {
+            Ok(None)
+        } else {
+            Err(anyhow!(
+                "failed sending alert to Google Chat: {} {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            ))
+        }
+    }
+}
+
+fn card(alert: &Alert<'_>) -> Value {
+    let mut widgets = vec![json!({
+        "textParagraph": {"text": alert.html_message().replace('\n', "<br>")},
+    })];
+    widgets.extend(
+        alert
+            .facts()
+            .into_iter()
+            .map(|(label, value)| json!({"decoratedText": {"topLabel": label, "text": value}})),
+    );
+
+    json!({
+        // shown in notifications, where cards aren't
+        "text": alert.title(),
+        "cardsV2": [{
+            "cardId": "kplc-bill-alert",
+            "card": {
+                "header": {"title": alert.title()},
+                "sections": [{"widgets": widgets}],
+            },
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use mockito::{mock, Matcher};
+    use pretty_assertions::assert_eq;
+    use reqwest::Client;
+    use serde_json::json;
+
+    use super::{GoogleChat, GoogleChatSettings};
+
+    fn make_google_chat() -> GoogleChat {
+        let settings = GoogleChatSettings {
+            enabled: true,
+            webhook_url: format!(
+                "{}/v1/spaces/AAAA/messages?key=asdasd&token=qwe123",
+                mockito::server_url()
+            ),
+        };
+
+        GoogleChat {
+            settings,
+            http_client: Client::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_successfully() {
+        let g = make_google_chat();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/v1/spaces/AAAA/messages")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("key".to_string(), "asdasd".to_string()),
+                Matcher::UrlEncoded("token".to_string(), "qwe123".to_string()),
+            ]))
+            .match_body(Matcher::Json(json!({
+                "text": "KPLC Bill (#1234567): 10 - October 2022",
+                "cardsV2": [{
+                    "cardId": "kplc-bill-alert",
+                    "card": {
+                        "header": {"title": "KPLC Bill (#1234567): 10 - October 2022"},
+                        "sections": [{"widgets": [
+                            {"textParagraph": {
+                                "text": "Balance of <b>KES 3592.34</b> is due on <b>25 October, 2022</b>!",
+                            }},
+                            {"decoratedText": {"topLabel": "Account", "text": "1234567"}},
+                            {"decoratedText": {"topLabel": "Balance", "text": "KES 3592.34 in arrears"}},
+                            {"decoratedText": {"topLabel": "Due date", "text": "25 October, 2022"}},
+                            {"decoratedText": {"topLabel": "Latest bill", "text": "10 - October 2022"}},
+                            {"decoratedText": {"topLabel": "Bill amount", "text": "KES 3593"}},
+                        ]}],
+                    },
+                }],
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"name\":\"spaces/AAAA/messages/BBBB\"}")
+            .create();
+
+        let result = g
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_error() {
+        let g = make_google_chat();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/v1/spaces/AAAA/messages")
+            .match_query(Matcher::Any)
+            .with_status(400)
+            .with_body("{\"error\":{\"code\":400,\"status\":\"INVALID_ARGUMENT\"}}")
+            .create();
+
+        let result = g
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("failed sending alert to Google Chat: 400 Bad Request"));
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use crate::{
+    alert::{Alert, Severity},
+    client,
+    settings::Settings,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::Channel;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct GotifySettings {
+    #[serde(default)]
+    pub enabled: bool,
+    pub server_url: String,
+    /// Token of the application the alerts are sent as.
+    pub app_token: String,
+    #[serde(default)]
+    pub markdown: bool,
+    /// Overrides the priority (0 to 10) alerts of a given severity are sent with.
+    #[serde(default)]
+    pub priority: HashMap<Severity, u8>,
+}
+
+/// Sends alerts as messages of an application on a Gotify server.
+pub struct Gotify {
+    settings: GotifySettings,
+    http_client: Client,
+}
+
+#[async_trait]
+impl Channel for Gotify {
+    fn new(settings: &Settings) -> Gotify {
+        Gotify::with_settings(settings.gotify.clone())
+    }
+
+    fn name(&self) -> &str {
+        "Gotify"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let mut body = json!({
+            "title": alert.title(),
+            "message": alert.message(),
+            "priority": self.priority(alert.severity()),
+        });
+        if self.settings.markdown {
+            body["message"] = json!(alert.markdown_message());
+            body["extras"] = json!({
+                "client::display": {"contentType": "text/markdown"},
+            });
+        }
+
+        let url = format!("{}/message", self.settings.server_url.trim_end_matches('/'));
+        let resp = self
+            .http_client
+            .post(url.as_str())
+            .header("X-Gotify-Key", self.settings.app_token.as_str())
+            .json(&body)
+            .send()
+            .await?;
+
+        if resp.status().is_success() {
+            Ok(None)
+        } else {
+            Err(anyhow!(
+                "failed sending alert to Gotify: {} {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            ))
+        }
+    }
+}
+
+impl Gotify {
+    pub fn with_settings(settings: GotifySettings) -> Gotify {
+        let http_client = client::get_self_hosted_http_client().unwrap();
+
+        Gotify {
+            settings,
+            http_client,
+        }
+    }
+
+    fn priority(&self, severity: Severity) -> u8 {
+        match self.settings.priority.get(&severity) {
+            Some(priority) => *priority,
+            None => match severity {
+                Severity::Low => 2,
+                Severity::Normal => 5,
+                Severity::High => 8,
+                Severity::Critical => 10,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use mockito::{mock, Matcher};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{Gotify, GotifySettings};
+
+    fn make_gotify() -> Gotify {
+        let settings = GotifySettings {
+            enabled: true,
+            server_url: format!("{}/gotify/", mockito::server_url()),
+            app_token: "AzXa1Ds".to_string(),
+            ..Default::default()
+        };
+
+        Gotify::with_settings(settings)
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_successfully() {
+        let g = make_gotify();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/gotify/message")
+            .match_header("x-gotify-key", "AzXa1Ds")
+            .match_body(Matcher::Json(json!({
+                "title": "KPLC Bill (#1234567): 10 - October 2022",
+                "message": "Balance of KES 3592.34 is due on 25 October, 2022!",
+                "priority": 10,
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\":25,\"appid\":5}")
+            .create();
+
+        let result = g
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_with_markdown() {
+        let mut g = make_gotify();
+        g.settings.markdown = true;
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/gotify/message")
+            .match_body(Matcher::PartialJson(json!({
+                "message": "Balance of **KES 3592.34** is due on **25 October, 2022**!",
+                "extras": {"client::display": {"contentType": "text/markdown"}},
+            })))
+            .with_status(200)
+            .create();
+
+        let result = g
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_error() {
+        let g = make_gotify();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/gotify/message")
+            .with_status(401)
+            .with_body("{\"error\":\"Unauthorized\",\"errorCode\":401}")
+            .create();
+
+        let result = g
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("failed sending alert to Gotify: 401 Unauthorized"));
+    }
+}
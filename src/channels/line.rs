@@ -0,0 +1,78 @@
+use chrono::prelude::{DateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use serde_json::json;
+
+use crate::alert::Alert;
+
+/// Renders the alert as a single line: JSON unless a template is given, in
+/// which `{sent_at}`, `{account}`, `{balance}`, `{severity}`, `{title}` and
+/// `{message}` are replaced.
+pub fn render(alert: &Alert<'_>, template: Option<&str>, now: DateTime<Utc>) -> String {
+    let bill = alert.bill;
+    let severity = format!("{:?}", alert.severity()).to_lowercase();
+
+    match template {
+        Some(template) => template
+            .replace("{sent_at}", &now.to_rfc3339())
+            .replace("{account}", &bill.data.account_reference)
+            .replace("{balance}", &bill.data.balance.to_string())
+            .replace("{severity}", &severity)
+            .replace("{title}", &alert.title())
+            // keep it to one line
+            .replace("{message}", &alert.message().replace('\n', " ")),
+        None => json!({
+            "sent_at": now.to_rfc3339(),
+            "account": bill.data.account_reference,
+            "balance": bill.data.balance.to_f64(),
+            "severity": severity,
+            "title": alert.title(),
+            "message": alert.message(),
+        })
+        .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::render;
+    use crate::{
+        alert::{Alert, AlertKind},
+        test_utils::get_kplc_bill_resp,
+    };
+
+    #[test]
+    fn test_render_json() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let alert = Alert::new(AlertKind::BalanceDue, &bill);
+        let now = Utc.with_ymd_and_hms(2022, 10, 20, 6, 0, 0).unwrap();
+
+        assert_eq!(
+            render(&alert, None, now),
+            "{\"account\":\"1234567\",\"balance\":-3592.34,\
+             \"message\":\"Balance of KES 3592.34 is due on 25 October, 2022!\",\
+             \"sent_at\":\"2022-10-20T06:00:00+00:00\",\"severity\":\"critical\",\
+             \"title\":\"KPLC Bill (#1234567): 10 - October 2022\"}"
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let alert =
+            Alert::new(AlertKind::BalanceDue, &bill).with_notes(vec!["Used 300 kWh.".to_string()]);
+        let now = Utc.with_ymd_and_hms(2022, 10, 20, 6, 0, 0).unwrap();
+
+        assert_eq!(
+            render(
+                &alert,
+                Some("{sent_at} [{severity}] {title}: {message}"),
+                now
+            ),
+            "2022-10-20T06:00:00+00:00 [critical] KPLC Bill (#1234567): 10 - October 2022: \
+             Balance of KES 3592.34 is due on 25 October, 2022! Used 300 kWh."
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{alert::Alert, client, dashboard, settings::Settings};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::Channel;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct MatrixSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    pub homeserver_url: String,
+    pub access_token: String,
+    /// ID of the room alerts are posted to, e.g. `!abcdef:matrix.org`.
+    pub room_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct MatrixErrorResponse {
+    errcode: String,
+    error: String,
+}
+
+/// Posts alerts to a Matrix room through the homeserver's client-server API.
+pub struct Matrix {
+    settings: MatrixSettings,
+    http_client: Client,
+}
+
+#[async_trait]
+impl Channel for Matrix {
+    fn new(settings: &Settings) -> Matrix {
+        Matrix::with_settings(settings.matrix.clone())
+    }
+
+    fn name(&self) -> &str {
+        "Matrix"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let title = alert.title();
+        let body = json!({
+            "msgtype": "m.text",
+            "body": format!("{title}\n{}", alert.message()),
+            "format": "org.matrix.custom.html",
+            "formatted_body": format!(
+                "<h4>{}</h4>{}",
+                dashboard::escape(&title),
+                alert.html_message().replace('\n', "<br>")
+            ),
+        });
+
+        let resp = self
+            .http_client
+            .put(self.send_url()?)
+            .bearer_auth(self.settings.access_token.as_str())
+            .json(&body)
+            .send()
+            .await?;
+
+        if resp.status().is_success() {
+            Ok(None)
+        } else {
+            let status = resp.status();
+            match resp.json::<MatrixErrorResponse>().await {
+                Ok(err) => Err(anyhow!(
+                    "failed sending alert to Matrix: {} ({})",
+                    err.error,
+                    err.errcode
+                )),
+                Err(_) => Err(anyhow!("failed sending alert to Matrix: {status}")),
+            }
+        }
+    }
+}
+
+impl Matrix {
+    pub fn with_settings(settings: MatrixSettings) -> Matrix {
+        let http_client = client::get_self_hosted_http_client().unwrap();
+
+        Matrix {
+            settings,
+            http_client,
+        }
+    }
+
+    /// URL of the endpoint that sends a message to the room, under a new
+    /// transaction ID so the homeserver doesn't discard it as a retry.
+    fn send_url(&self) -> Result<Url> {
+        let mut url = Url::parse(self.settings.homeserver_url.as_str())?;
+        let txn_id = format!(
+            "kplc-bill-alert-{}",
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        );
+
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("invalid Matrix homeserver URL"))?
+            .pop_if_empty()
+            .extend([
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                self.settings.room_id.as_str(),
+                "send",
+                "m.room.message",
+                txn_id.as_str(),
+            ]);
+
+        Ok(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use mockito::{mock, Matcher};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{Matrix, MatrixSettings};
+
+    fn make_matrix() -> Matrix {
+        let settings = MatrixSettings {
+            enabled: true,
+            homeserver_url: format!("{}/", mockito::server_url()),
+            access_token: "syt_asdasd".to_string(),
+            room_id: "!bills:example.org".to_string(),
+        };
+
+        Matrix::with_settings(settings)
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_successfully() {
+        let m = make_matrix();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let mock = mock(
+            "PUT",
+            Matcher::Regex(
+                r"^/_matrix/client/v3/rooms/!bills:example.org/send/m.room.message/kplc-bill-alert-\d+$"
+                    .to_string(),
+            ),
+        )
+        .match_header("authorization", "Bearer syt_asdasd")
+        .match_body(Matcher::Json(json!({
+            "msgtype": "m.text",
+            "body": "KPLC Bill (#1234567): 10 - October 2022\n\
+                     Balance of KES 3592.34 is due on 25 October, 2022!",
+            "format": "org.matrix.custom.html",
+            "formatted_body": "<h4>KPLC Bill (#1234567): 10 - October 2022</h4>\
+                               Balance of <b>KES 3592.34</b> is due on <b>25 October, 2022</b>!",
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"event_id\":\"$YUwRidLecu:example.org\"}")
+        .create();
+
+        let result = m
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        mock.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_escapes_title() {
+        let m = make_matrix();
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        bill.data.col_bills[0].billing_period = "<10 - October 2022>".to_string();
+
+        let mock = mock("PUT", Matcher::Any)
+            .match_body(Matcher::PartialJson(json!({
+                "formatted_body": "<h4>KPLC Bill (#1234567): &lt;10 - October 2022&gt;</h4>\
+                                   Balance of <b>KES 3592.34</b> is due on <b>25 October, 2022</b>!",
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"event_id\":\"$YUwRidLecu:example.org\"}")
+            .create();
+
+        let result = m
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        mock.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_error() {
+        let m = make_matrix();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let mock = mock("PUT", Matcher::Any)
+            .with_status(403)
+            .with_header("content-type", "application/json")
+            .with_body(
+                "{\"errcode\":\"M_FORBIDDEN\",\"error\":\"User not in room !bills:example.org\"}",
+            )
+            .create();
+
+        let result = m
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        mock.assert();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "failed sending alert to Matrix: User not in room !bills:example.org (M_FORBIDDEN)"
+        );
+    }
+}
@@ -1,11 +1,37 @@
-use crate::{kplc::KPLCBill, settings::Settings};
-use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::{alert::Alert, kplc::KPLCBill, settings::Settings};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use chrono::prelude::{DateTime, Utc};
 
+pub mod apprise;
+pub mod email;
+pub mod exec;
+pub mod file;
+pub mod google_chat;
+pub mod gotify;
+pub mod line;
+pub mod matrix;
+pub mod mqtt;
+pub mod ntfy;
 pub mod pushover;
+pub mod signal;
+pub mod stdout;
+pub mod teams;
+pub mod telegram;
+pub mod whatsapp;
+
+/// Acknowledgement status of an alert sent with a receipt.
+#[derive(Debug, Default, PartialEq)]
+pub struct ReceiptStatus {
+    pub acknowledged_by: Option<String>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub expired: bool,
+}
 
 #[async_trait]
-pub trait Channel {
+pub trait Channel: Send + Sync {
     fn new(settings: &Settings) -> Self
     where
         Self: Sized;
@@ -16,9 +42,68 @@ pub trait Channel {
         false
     }
 
-    async fn send_alert(&self, message: &KPLCBill) -> Result<()>;
+    /// Sends the alert, returning a receipt if the channel tracks its acknowledgement.
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>>;
+
+    /// Publishes the bill's current state, on every run whether or not an alert is due.
+    async fn publish_status(&self, _bill: &KPLCBill) -> Result<()> {
+        Ok(())
+    }
+
+    async fn check_receipt(&self, _receipt: &str) -> Result<ReceiptStatus> {
+        Err(anyhow!("{} doesn't issue receipts", self.name()))
+    }
+
+    /// Stops the channel from re-sending the alert behind the receipt.
+    async fn cancel_receipt(&self, _receipt: &str) -> Result<()> {
+        Ok(())
+    }
 }
 
 pub fn get_channels(settings: &Settings) -> Vec<Box<dyn Channel>> {
-    vec![Box::new(pushover::Pushover::new(settings))]
+    let mut channels: Vec<Box<dyn Channel>> = vec![
+        Box::new(pushover::Pushover::new(settings)),
+        Box::new(mqtt::Mqtt::new(settings)),
+        Box::new(ntfy::Ntfy::new(settings)),
+        Box::new(gotify::Gotify::new(settings)),
+        Box::new(matrix::Matrix::new(settings)),
+        Box::new(teams::Teams::new(settings)),
+        Box::new(google_chat::GoogleChat::new(settings)),
+        Box::new(whatsapp::WhatsApp::new(settings)),
+        Box::new(signal::Signal::new(settings)),
+        Box::new(telegram::Telegram::new(settings)),
+        Box::new(email::Email::new(settings)),
+        Box::new(exec::Exec::new(settings)),
+        Box::new(stdout::Stdout::new(settings)),
+        Box::new(file::File::new(settings)),
+    ];
+    channels.extend(
+        settings
+            .apprise
+            .urls
+            .iter()
+            .map(apprise::AppriseUrl::channel),
+    );
+
+    channels
+}
+
+/// Identifies each channel by its name, numbering those sharing one (such as a
+/// `pover://` URL alongside the `[pushover]` section) in the order they're
+/// configured, so that receipts get checked on the channel that issued them.
+pub fn channel_ids(channels: &[Box<dyn Channel>]) -> Vec<String> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+
+    channels
+        .iter()
+        .map(|channel| {
+            let count = seen.entry(channel.name()).or_default();
+            *count += 1;
+            if *count == 1 {
+                channel.name().to_string()
+            } else {
+                format!("{} #{count}", channel.name())
+            }
+        })
+        .collect()
 }
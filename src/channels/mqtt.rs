@@ -0,0 +1,339 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use rust_decimal::prelude::ToPrimitive;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{alert::Alert, kplc::KPLCBill, settings::Settings};
+
+use super::Channel;
+
+// how long to wait on the broker before giving up on publishing
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MqttSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Prefix of the topics state and alerts are published on.
+    #[serde(default = "default_topic_prefix")]
+    pub topic_prefix: String,
+    /// Prefix Home Assistant watches for discovery config.
+    #[serde(default = "default_discovery_prefix")]
+    pub discovery_prefix: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        MqttSettings {
+            enabled: false,
+            host: default_host(),
+            port: default_port(),
+            client_id: default_client_id(),
+            username: None,
+            password: None,
+            topic_prefix: default_topic_prefix(),
+            discovery_prefix: default_discovery_prefix(),
+        }
+    }
+}
+
+fn default_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+fn default_client_id() -> String {
+    "kplc-bill-alert".to_string()
+}
+
+fn default_topic_prefix() -> String {
+    "kplc".to_string()
+}
+
+fn default_discovery_prefix() -> String {
+    "homeassistant".to_string()
+}
+
+/// A message to publish: topic, payload and whether the broker retains it.
+type Message = (String, String, bool);
+
+/// Publishes account state to an MQTT broker, along with Home Assistant
+/// discovery config so the account shows up as a device with sensors.
+pub struct Mqtt {
+    settings: MqttSettings,
+}
+
+#[async_trait]
+impl Channel for Mqtt {
+    fn new(settings: &Settings) -> Mqtt {
+        Mqtt::with_settings(settings.mqtt.clone())
+    }
+
+    fn name(&self) -> &str {
+        "MQTT"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let account_ref = alert.bill.data.account_reference.as_str();
+        let payload = json!({
+            "title": alert.title(),
+            "message": alert.message(),
+            "severity": format!("{:?}", alert.severity()).to_lowercase(),
+        });
+
+        self.publish(vec![(
+            format!("{}/{account_ref}/alert", self.settings.topic_prefix),
+            payload.to_string(),
+            false,
+        )])
+        .await?;
+
+        Ok(None)
+    }
+
+    async fn publish_status(&self, bill: &KPLCBill) -> Result<()> {
+        let mut messages = self.discovery_messages(bill);
+        messages.push(self.state_message(bill));
+
+        self.publish(messages).await
+    }
+}
+
+impl Mqtt {
+    pub fn with_settings(settings: MqttSettings) -> Mqtt {
+        Mqtt { settings }
+    }
+
+    fn state_topic(&self, account_ref: &str) -> String {
+        format!("{}/{account_ref}/state", self.settings.topic_prefix)
+    }
+
+    fn state_message(&self, bill: &KPLCBill) -> Message {
+        let account_ref = bill.data.account_reference.as_str();
+        // the oldest unpaid bill is the first to fall due
+        let due_date = bill
+            .unpaid_bills()
+            .last()
+            .map(|unpaid_bill| unpaid_bill.due_date.format("%Y-%m-%d").to_string());
+        let latest_bill = bill.data.col_bills.first();
+        let meter_reading = bill
+            .data
+            .meter_list
+            .iter()
+            .flat_map(|meter| meter.latest_usage_list.iter())
+            .max_by_key(|usage| usage.reading_date)
+            .map(|usage| usage.reading_value);
+
+        let payload = json!({
+            "balance": bill.data.balance.to_f64(),
+            "due_date": due_date,
+            "last_bill_amount": latest_bill.and_then(|b| b.bill_amount.to_f64()),
+            "billing_period": latest_bill.map(|b| b.billing_period.as_str()),
+            "meter_reading": meter_reading,
+        });
+
+        (self.state_topic(account_ref), payload.to_string(), true)
+    }
+
+    fn discovery_messages(&self, bill: &KPLCBill) -> Vec<Message> {
+        let account_ref = bill.data.account_reference.as_str();
+        let device_id = format!("kplc_{account_ref}");
+        let device = json!({
+            "identifiers": [device_id],
+            "name": format!("KPLC {account_ref}"),
+            "manufacturer": "Kenya Power",
+        });
+
+        let sensors = [
+            (
+                "balance",
+                "Balance",
+                json!({"unit_of_measurement": "KES", "device_class": "monetary"}),
+            ),
+            ("due_date", "Due date", json!({"device_class": "date"})),
+            (
+                "last_bill_amount",
+                "Last bill amount",
+                json!({"unit_of_measurement": "KES", "device_class": "monetary"}),
+            ),
+            (
+                "meter_reading",
+                "Meter reading",
+                json!({
+                    "unit_of_measurement": "kWh",
+                    "device_class": "energy",
+                    "state_class": "total_increasing",
+                }),
+            ),
+        ];
+
+        sensors
+            .into_iter()
+            .map(|(key, name, extra)| {
+                let mut config = json!({
+                    "name": name,
+                    "unique_id": format!("{device_id}_{key}"),
+                    "state_topic": self.state_topic(account_ref),
+                    "value_template": format!("{{{{ value_json.{key} }}}}"),
+                    "device": device,
+                });
+                if let (Value::Object(config), Value::Object(extra)) = (&mut config, extra) {
+                    config.extend(extra);
+                }
+
+                (
+                    format!(
+                        "{}/sensor/{device_id}/{key}/config",
+                        self.settings.discovery_prefix
+                    ),
+                    config.to_string(),
+                    true,
+                )
+            })
+            .collect()
+    }
+
+    /// Connects to the broker and publishes the messages, waiting for each to
+    /// be acknowledged.
+    async fn publish(&self, messages: Vec<Message>) -> Result<()> {
+        let mut options = MqttOptions::new(
+            self.settings.client_id.as_str(),
+            self.settings.host.as_str(),
+            self.settings.port,
+        );
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &self.settings.username {
+            options.set_credentials(
+                username.as_str(),
+                self.settings.password.as_deref().unwrap_or_default(),
+            );
+        }
+
+        let (client, mut eventloop) = AsyncClient::new(options, messages.len().max(1));
+        let mut pending = messages.len();
+        for (topic, payload, retain) in messages {
+            client
+                .publish(topic, QoS::AtLeastOnce, retain, payload)
+                .await?;
+        }
+
+        let acknowledged = async {
+            while pending > 0 {
+                match eventloop.poll().await {
+                    Ok(Event::Incoming(Packet::PubAck(_))) => pending -= 1,
+                    Ok(_) => {}
+                    Err(err) => return Err(anyhow!(err)),
+                }
+            }
+            client.disconnect().await?;
+
+            Ok(())
+        };
+
+        tokio::time::timeout(PUBLISH_TIMEOUT, acknowledged)
+            .await
+            .map_err(|_| anyhow!("timed out"))?
+            .with_context(|| {
+                format!(
+                    "error publishing to MQTT broker {}:{}",
+                    self.settings.host, self.settings.port
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::{Mqtt, MqttSettings};
+    use crate::{channels::Channel, test_utils::get_kplc_bill_resp};
+
+    fn make_mqtt() -> Mqtt {
+        Mqtt {
+            settings: MqttSettings {
+                enabled: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_state_message() {
+        let mqtt = make_mqtt();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let (topic, payload, retain) = mqtt.state_message(&bill);
+        assert_eq!(topic, "kplc/1234567/state");
+        assert!(retain);
+        assert_eq!(
+            serde_json::from_str::<Value>(&payload).unwrap(),
+            json!({
+                "balance": -3592.34,
+                "due_date": "2022-10-25",
+                "last_bill_amount": 3593.0,
+                "billing_period": "10 - October 2022",
+                "meter_reading": 18234,
+            })
+        );
+    }
+
+    #[test]
+    fn test_discovery_messages() {
+        let mqtt = make_mqtt();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let messages = mqtt.discovery_messages(&bill);
+        assert_eq!(messages.len(), 4);
+
+        let (topic, payload, retain) = &messages[0];
+        assert_eq!(topic, "homeassistant/sensor/kplc_1234567/balance/config");
+        assert!(retain);
+        assert_eq!(
+            serde_json::from_str::<Value>(payload).unwrap(),
+            json!({
+                "name": "Balance",
+                "unique_id": "kplc_1234567_balance",
+                "state_topic": "kplc/1234567/state",
+                "value_template": "{{ value_json.balance }}",
+                "unit_of_measurement": "KES",
+                "device_class": "monetary",
+                "device": {
+                    "identifiers": ["kplc_1234567"],
+                    "name": "KPLC 1234567",
+                    "manufacturer": "Kenya Power",
+                },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_publish_without_broker() {
+        let mut mqtt = make_mqtt();
+        mqtt.settings.port = 1;
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let result = mqtt.publish_status(&bill).await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use crate::{
+    alert::{Alert, Severity},
+    client,
+    settings::Settings,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::Channel;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct NtfySettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_server_url")]
+    pub server_url: String,
+    pub topic: String,
+    /// Access token for topics that require authentication.
+    pub token: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub markdown: bool,
+    /// Overrides the priority (1 to 5) alerts of a given severity are sent with.
+    #[serde(default)]
+    pub priority: HashMap<Severity, u8>,
+}
+
+impl Default for NtfySettings {
+    fn default() -> Self {
+        NtfySettings {
+            enabled: false,
+            server_url: default_server_url(),
+            topic: String::new(),
+            token: None,
+            tags: vec![],
+            markdown: false,
+            priority: HashMap::new(),
+        }
+    }
+}
+
+fn default_server_url() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+/// Publishes alerts to a topic on an ntfy server.
+pub struct Ntfy {
+    settings: NtfySettings,
+    http_client: Client,
+}
+
+#[async_trait]
+impl Channel for Ntfy {
+    fn new(settings: &Settings) -> Ntfy {
+        Ntfy::with_settings(settings.ntfy.clone())
+    }
+
+    fn name(&self) -> &str {
+        "ntfy"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let message = if self.settings.markdown {
+            alert.markdown_message()
+        } else {
+            alert.message()
+        };
+        let body = json!({
+            "topic": self.settings.topic,
+            "title": alert.title(),
+            "message": message,
+            "priority": self.priority(alert.severity()),
+            "tags": self.settings.tags,
+            "markdown": self.settings.markdown,
+        });
+
+        let mut request = self
+            .http_client
+            .post(self.settings.server_url.as_str())
+            .json(&body);
+        if let Some(token) = &self.settings.token {
+            request = request.bearer_auth(token);
+        }
+
+        let resp = request.send().await?;
+        if resp.status().is_success() {
+            Ok(None)
+        } else {
+            Err(anyhow!(
+                "failed sending alert to ntfy: {} {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            ))
+        }
+    }
+}
+
+impl Ntfy {
+    pub fn with_settings(settings: NtfySettings) -> Ntfy {
+        let http_client = client::get_self_hosted_http_client().unwrap();
+
+        Ntfy {
+            settings,
+            http_client,
+        }
+    }
+
+    fn priority(&self, severity: Severity) -> u8 {
+        match self.settings.priority.get(&severity) {
+            Some(priority) => *priority,
+            None => match severity {
+                Severity::Low => 2,
+                Severity::Normal => 3,
+                Severity::High => 4,
+                Severity::Critical => 5,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        alert::{Alert, AlertKind, Severity},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use mockito::{mock, Matcher};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{Ntfy, NtfySettings};
+
+    fn make_ntfy() -> Ntfy {
+        let settings = NtfySettings {
+            enabled: true,
+            server_url: format!("{}/ntfy", mockito::server_url()),
+            topic: "kplc".to_string(),
+            token: Some("tk_asdasd".to_string()),
+            tags: vec!["zap".to_string()],
+            ..Default::default()
+        };
+
+        Ntfy::with_settings(settings)
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_successfully() {
+        let n = make_ntfy();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/ntfy")
+            .match_header("authorization", "Bearer tk_asdasd")
+            .match_body(Matcher::Json(json!({
+                "topic": "kplc",
+                "title": "KPLC Bill (#1234567): 10 - October 2022",
+                "message": "Balance of KES 3592.34 is due on 25 October, 2022!",
+                "priority": 5,
+                "tags": ["zap"],
+                "markdown": false,
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\":\"sPs71M8A2T\",\"event\":\"message\"}")
+            .create();
+
+        let result = n
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_with_markdown_and_priority() {
+        let mut n = make_ntfy();
+        n.settings.markdown = true;
+        n.settings.priority = HashMap::from([(Severity::Critical, 4)]);
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/ntfy")
+            .match_body(Matcher::PartialJson(json!({
+                "message": "Balance of **KES 3592.34** is due on **25 October, 2022**!",
+                "priority": 4,
+                "markdown": true,
+            })))
+            .with_status(200)
+            .create();
+
+        let result = n
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_error() {
+        let n = make_ntfy();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/ntfy")
+            .with_status(403)
+            .with_body("{\"code\":40301,\"error\":\"forbidden\"}")
+            .create();
+
+        let result = n
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "failed sending alert to ntfy: 403 Forbidden {\"code\":40301,\"error\":\"forbidden\"}"
+        );
+    }
+}
@@ -1,17 +1,93 @@
-use crate::{client, kplc::KPLCBill, settings::Settings};
+use std::collections::HashMap;
+
+use crate::{
+    alert::{Alert, Severity},
+    chart, client,
+    credit::Balance,
+    kplc::KPLCBill,
+    settings::Settings,
+};
 use anyhow::{anyhow, Ok, Result};
 use async_trait::async_trait;
-use reqwest::Client;
+use chrono::{TimeZone, Utc};
+use log::warn;
+use reqwest::{multipart, Client};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
 use serde::Deserialize;
 
-use super::Channel;
+use super::{Channel, ReceiptStatus};
+
+// priority at which Pushover keeps re-sending the alert until it's acknowledged
+const EMERGENCY_PRIORITY: i8 = 2;
+// defaults for emergency alerts, in seconds, when `retry`/`expire` aren't configured
+const DEFAULT_EMERGENCY_RETRY: u32 = 300;
+const DEFAULT_EMERGENCY_EXPIRE: u32 = 3600;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct PushoverSettings {
+    #[serde(default)]
     pub enabled: bool,
+    #[serde(default = "default_api_url")]
     pub api_url: String,
     pub token: String,
     pub user_key: String,
+    #[serde(default = "default_receipts_url")]
+    pub receipts_url: String,
+    #[serde(default = "default_glances_url")]
+    pub glances_url: String,
+    pub device: Option<String>,
+    pub url: Option<String>,
+    pub url_title: Option<String>,
+    #[serde(default)]
+    pub html: bool,
+    #[serde(default)]
+    pub attach_chart: bool,
+    #[serde(default)]
+    pub glance: bool,
+    #[serde(default)]
+    pub severity: HashMap<Severity, PushoverMessageSettings>,
+}
+
+impl Default for PushoverSettings {
+    fn default() -> Self {
+        PushoverSettings {
+            enabled: false,
+            api_url: default_api_url(),
+            token: String::new(),
+            user_key: String::new(),
+            receipts_url: default_receipts_url(),
+            glances_url: default_glances_url(),
+            device: None,
+            url: None,
+            url_title: None,
+            html: false,
+            attach_chart: false,
+            glance: false,
+            severity: HashMap::new(),
+        }
+    }
+}
+
+fn default_api_url() -> String {
+    "https://api.pushover.net/1/messages.json".to_string()
+}
+
+fn default_receipts_url() -> String {
+    "https://api.pushover.net/1/receipts".to_string()
+}
+
+fn default_glances_url() -> String {
+    "https://api.pushover.net/1/glances.json".to_string()
+}
+
+/// Message options applied to alerts of a given severity.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PushoverMessageSettings {
+    pub priority: Option<i8>,
+    pub sound: Option<String>,
+    pub retry: Option<u32>,
+    pub expire: Option<u32>,
+    pub ttl: Option<u32>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -19,6 +95,24 @@ pub struct PushoverSettings {
 struct PushoverResponse {
     status: usize,
     request: String,
+    receipt: Option<String>,
+    errors: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+struct PushoverReceiptResponse {
+    status: usize,
+    #[serde(default)]
+    acknowledged: u8,
+    #[serde(default)]
+    acknowledged_at: i64,
+    #[serde(default)]
+    acknowledged_by: String,
+    #[serde(default)]
+    acknowledged_by_device: String,
+    #[serde(default)]
+    expired: u8,
     errors: Option<Vec<String>>,
 }
 
@@ -30,12 +124,7 @@ pub struct Pushover {
 #[async_trait]
 impl Channel for Pushover {
     fn new(settings: &Settings) -> Pushover {
-        let http_client = client::get_http_client().unwrap();
-
-        Pushover {
-            settings: settings.pushover.clone(),
-            http_client,
-        }
+        Pushover::with_settings(settings.pushover.clone())
     }
 
     fn name(&self) -> &str {
@@ -46,64 +135,258 @@ impl Channel for Pushover {
         self.settings.enabled
     }
 
-    async fn send_alert(&self, bill: &KPLCBill) -> Result<(), anyhow::Error> {
-        let title = self.get_title(bill);
-        let message = self.get_message(bill);
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>, anyhow::Error> {
+        let title = alert.title();
+        let message = if self.settings.html {
+            alert.html_message()
+        } else {
+            alert.message()
+        };
+
+        let mut params = vec![
+            ("token", self.settings.token.clone()),
+            ("user", self.settings.user_key.clone()),
+            ("title", title),
+            ("message", message),
+        ];
+        params.extend(self.get_options(alert.severity()));
+
+        let request = self.http_client.post(self.settings.api_url.as_str());
+        let request = match self.get_chart(alert.bill) {
+            Some(png) => {
+                let mut form = multipart::Form::new();
+                for (name, value) in params {
+                    form = form.text(name, value);
+                }
+                let attachment = multipart::Part::bytes(png)
+                    .file_name("bills.png")
+                    .mime_str("image/png")?;
+
+                request.multipart(form.part("attachment", attachment))
+            }
+            None => request.form(&params),
+        };
+
+        let resp = request.send().await?.json::<PushoverResponse>().await?;
+
+        // status code of `1` means the request was successfull
+        if resp.status == 1 {
+            // only emergency priority alerts come with a receipt
+            Ok(resp.receipt)
+        } else {
+            Err(anyhow!(
+                "failed sending alert to Pushover: {:?}",
+                resp.errors.unwrap()
+            ))
+        }
+    }
 
-        let params = &[
-            ("token", self.settings.token.as_str()),
-            ("user", self.settings.user_key.as_str()),
-            ("title", title.as_str()),
-            ("message", message.as_str()),
+    async fn publish_status(&self, bill: &KPLCBill) -> Result<()> {
+        if !self.settings.glance {
+            return Ok(());
+        }
+
+        let mut params = vec![
+            ("token", self.settings.token.clone()),
+            ("user", self.settings.user_key.clone()),
         ];
+        params.extend(self.get_glance(bill));
+        if let Some(device) = &self.settings.device {
+            params.push(("device", device.clone()));
+        }
 
         let resp = self
             .http_client
-            .post(self.settings.api_url.as_str())
+            .post(self.settings.glances_url.as_str())
             .form(&params)
             .send()
             .await?
             .json::<PushoverResponse>()
             .await?;
 
-        // status code of `1` means the request was successfull
         if resp.status == 1 {
             Ok(())
         } else {
             Err(anyhow!(
-                "failed sending alert to Pushover: {:?}",
-                resp.errors.unwrap()
+                "failed updating Pushover glance: {:?}",
+                resp.errors.unwrap_or_default()
+            ))
+        }
+    }
+
+    async fn check_receipt(&self, receipt: &str) -> Result<ReceiptStatus> {
+        let url = format!("{}/{receipt}.json", self.settings.receipts_url);
+
+        let resp = self
+            .http_client
+            .get(url.as_str())
+            .query(&[("token", self.settings.token.as_str())])
+            .send()
+            .await?
+            .json::<PushoverReceiptResponse>()
+            .await?;
+
+        if resp.status != 1 {
+            return Err(anyhow!(
+                "failed checking Pushover receipt {receipt}: {:?}",
+                resp.errors.unwrap_or_default()
+            ));
+        }
+
+        if resp.acknowledged == 1 {
+            let acknowledged_by = if resp.acknowledged_by_device.is_empty() {
+                resp.acknowledged_by
+            } else {
+                format!("{} ({})", resp.acknowledged_by, resp.acknowledged_by_device)
+            };
+
+            Ok(ReceiptStatus {
+                acknowledged_by: Some(acknowledged_by),
+                acknowledged_at: Utc.timestamp_opt(resp.acknowledged_at, 0).single(),
+                expired: resp.expired == 1,
+            })
+        } else {
+            Ok(ReceiptStatus {
+                expired: resp.expired == 1,
+                ..Default::default()
+            })
+        }
+    }
+
+    async fn cancel_receipt(&self, receipt: &str) -> Result<()> {
+        let url = format!("{}/{receipt}/cancel.json", self.settings.receipts_url);
+
+        let resp = self
+            .http_client
+            .post(url.as_str())
+            .form(&[("token", self.settings.token.as_str())])
+            .send()
+            .await?
+            .json::<PushoverResponse>()
+            .await?;
+
+        if resp.status == 1 {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "failed cancelling Pushover receipt {receipt}: {:?}",
+                resp.errors.unwrap_or_default()
             ))
         }
     }
 }
 
 impl Pushover {
-    fn get_title(&self, bill: &KPLCBill) -> String {
+    pub fn with_settings(settings: PushoverSettings) -> Pushover {
+        let http_client = client::get_http_client().unwrap();
+
+        Pushover {
+            settings,
+            http_client,
+        }
+    }
+
+    fn get_chart(&self, bill: &KPLCBill) -> Option<Vec<u8>> {
+        if !self.settings.attach_chart {
+            return None;
+        }
+
+        match chart::bill_amounts_png(&bill.data.col_bills) {
+            Result::Ok(png) => Some(png),
+            Err(err) => {
+                warn!("not attaching bills chart: {}", err);
+                None
+            }
+        }
+    }
+
+    fn get_glance(&self, bill: &KPLCBill) -> Vec<(&'static str, String)> {
         let account_ref = bill.data.account_reference.as_str();
-        let billing_period = bill.data.col_bills[0].billing_period.as_str();
+        let balance = Balance::of(bill.data.balance);
 
-        format!("KPLC Bill (#{account_ref}): {billing_period}")
+        let (text, owed) = match balance {
+            Balance::Arrears(amount) => (format!("KES {amount} due"), amount),
+            Balance::Credit(amount) => (format!("KES {amount} credit"), Decimal::ZERO),
+            Balance::Settled => ("No balance due".to_string(), Decimal::ZERO),
+        };
+        // the oldest unpaid bill is the first to fall due
+        let subtext = match (bill.unpaid_bills().last(), bill.data.col_bills.first()) {
+            (Some(unpaid_bill), _) if !owed.is_zero() => {
+                format!("Due on {}", unpaid_bill.due_date.format("%d %b, %Y"))
+            }
+            (_, Some(latest_bill)) => latest_bill.billing_period.clone(),
+            _ => String::new(),
+        };
+
+        vec![
+            ("title", format!("KPLC #{account_ref}")),
+            ("text", text),
+            ("subtext", subtext),
+            (
+                "count",
+                owed.round().to_i64().unwrap_or_default().to_string(),
+            ),
+        ]
     }
 
-    fn get_message(&self, bill: &KPLCBill) -> String {
-        let balance = bill.data.balance.abs();
-        let due_date = bill.data.col_bills[0].due_date.format("%d %B, %Y");
+    fn get_options(&self, severity: Severity) -> Vec<(&'static str, String)> {
+        let mut options = vec![];
 
-        format!("Balance of KES {balance} is due on {due_date}!")
+        if let Some(device) = &self.settings.device {
+            options.push(("device", device.clone()));
+        }
+        if let Some(url) = &self.settings.url {
+            options.push(("url", url.clone()));
+        }
+        if let Some(url_title) = &self.settings.url_title {
+            options.push(("url_title", url_title.clone()));
+        }
+        if self.settings.html {
+            options.push(("html", "1".to_string()));
+        }
+
+        let message_settings = match self.settings.severity.get(&severity) {
+            Some(message_settings) => message_settings,
+            None => return options,
+        };
+
+        if let Some(priority) = message_settings.priority {
+            options.push(("priority", priority.to_string()));
+
+            // emergency alerts are rejected unless `retry` and `expire` are present
+            if priority == EMERGENCY_PRIORITY {
+                let retry = message_settings.retry.unwrap_or(DEFAULT_EMERGENCY_RETRY);
+                let expire = message_settings.expire.unwrap_or(DEFAULT_EMERGENCY_EXPIRE);
+                options.push(("retry", retry.to_string()));
+                options.push(("expire", expire.to_string()));
+            }
+        }
+        if let Some(sound) = &message_settings.sound {
+            options.push(("sound", sound.clone()));
+        }
+        if let Some(ttl) = message_settings.ttl {
+            options.push(("ttl", ttl.to_string()));
+        }
+
+        options
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{env, fs::File, path::Path};
+    use std::collections::HashMap;
 
-    use crate::{channels::Channel, kplc::KPLCBill};
-    use mockito::mock;
+    use crate::{
+        alert::{Alert, AlertKind, Severity},
+        channels::{Channel, ReceiptStatus},
+        test_utils::get_kplc_bill_resp,
+    };
+    use chrono::{TimeZone, Utc};
+    use mockito::{mock, Matcher};
     use pretty_assertions::assert_eq;
     use reqwest::Client;
 
-    use super::{Pushover, PushoverSettings};
+    use super::{Pushover, PushoverMessageSettings, PushoverSettings};
 
     fn make_pushover() -> Pushover {
         let settings = PushoverSettings {
@@ -111,6 +394,15 @@ mod tests {
             api_url: mockito::server_url(),
             token: "asdasd".to_string(),
             user_key: "a1213qd".to_string(),
+            receipts_url: format!("{}/receipts", mockito::server_url()),
+            glances_url: format!("{}/glances.json", mockito::server_url()),
+            device: None,
+            url: None,
+            url_title: None,
+            html: false,
+            attach_chart: false,
+            glance: false,
+            severity: HashMap::new(),
         };
         let http_client = Client::new();
 
@@ -120,15 +412,6 @@ mod tests {
         }
     }
 
-    fn get_kplc_bill_resp(filename: &str) -> KPLCBill {
-        let base_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-        let filepath = format!("{base_dir}/resources/test/{filename}");
-        let path = Path::new(filepath.as_str());
-        let file = File::open(path).unwrap();
-
-        serde_json::from_reader(file).unwrap()
-    }
-
     #[tokio::test]
     async fn test_send_alert_successfully() {
         let p = make_pushover();
@@ -151,7 +434,123 @@ mod tests {
             .with_body("{\"status\":1,\"request\":\"647d2300-702c-4b38-8b2f-d56326ae460b\"}")
             .create();
 
-        let result = p.send_alert(&bill).await;
+        let result = p
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_with_message_options() {
+        let mut p = make_pushover();
+        p.settings.device = Some("phone".to_string());
+        p.settings.url = Some("https://selfservice.kplc.co.ke".to_string());
+        p.settings.url_title = Some("Pay bill".to_string());
+        p.settings.severity.insert(
+            Severity::Normal,
+            PushoverMessageSettings {
+                priority: Some(0),
+                ..Default::default()
+            },
+        );
+        p.settings.severity.insert(
+            Severity::Critical,
+            PushoverMessageSettings {
+                priority: Some(2),
+                sound: Some("siren".to_string()),
+                retry: Some(60),
+                ..Default::default()
+            },
+        );
+        // the bill's due date is long past, so the alert is sent as critical
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("token", "asdasd")
+            .append_pair("user", "a1213qd")
+            .append_pair("title", "KPLC Bill (#1234567): 10 - October 2022")
+            .append_pair(
+                "message",
+                "Balance of KES 3592.34 is due on 25 October, 2022!",
+            )
+            .append_pair("device", "phone")
+            .append_pair("url", "https://selfservice.kplc.co.ke")
+            .append_pair("url_title", "Pay bill")
+            .append_pair("priority", "2")
+            .append_pair("retry", "60")
+            .append_pair("expire", "3600")
+            .append_pair("sound", "siren")
+            .finish();
+
+        let m = mock("POST", "/")
+            .match_body(body.as_str())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"status\":1,\"request\":\"647d2300-702c-4b38-8b2f-d56326ae460b\",\"receipt\":\"rLqVuqTRh62UzxtmqiaLzQmVcPgiCy\"}")
+            .create();
+
+        let result = p
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(
+            result.unwrap().as_deref(),
+            Some("rLqVuqTRh62UzxtmqiaLzQmVcPgiCy")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_with_chart() {
+        let mut p = make_pushover();
+        p.settings.attach_chart = true;
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/")
+            .match_header(
+                "content-type",
+                Matcher::Regex("^multipart/form-data; boundary=".to_string()),
+            )
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex(r#"name="title"\r\n\r\nKPLC Bill \(#1234567\)"#.to_string()),
+                Matcher::Regex(
+                    r#"name="attachment"; filename="bills.png"\r\nContent-Type: image/png"#
+                        .to_string(),
+                ),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"status\":1,\"request\":\"647d2300-702c-4b38-8b2f-d56326ae460b\"}")
+            .create();
+
+        let result = p
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_publish_status_updates_glance() {
+        let mut p = make_pushover();
+        p.settings.glance = true;
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("token", "asdasd")
+            .append_pair("user", "a1213qd")
+            .append_pair("title", "KPLC #1234567")
+            .append_pair("text", "KES 3592.34 due")
+            .append_pair("subtext", "Due on 25 Oct, 2022")
+            .append_pair("count", "3592")
+            .finish();
+
+        let m = mock("POST", "/glances.json")
+            .match_body(body.as_str())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"status\":1,\"request\":\"647d2300-702c-4b38-8b2f-d56326ae460b\"}")
+            .create();
+
+        let result = p.publish_status(&bill).await;
         m.assert();
         assert!(result.is_ok());
     }
@@ -167,7 +566,9 @@ mod tests {
             .with_body("{\"user\":\"invalid\",\"errors\":[\"user identifier is invalid\"],\"status\":0,\"request\":\"5042853c-402d-4a18-abcb-168734a801de\"}")
             .create();
 
-        let result = p.send_alert(&bill).await;
+        let result = p
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
         m.assert();
         assert!(result.is_err());
         assert_eq!(
@@ -175,4 +576,60 @@ mod tests {
             "failed sending alert to Pushover: [\"user identifier is invalid\"]"
         );
     }
+
+    #[tokio::test]
+    async fn test_check_receipt_acknowledged() {
+        let p = make_pushover();
+
+        let m = mock("GET", "/receipts/rLqVuqTRh62UzxtmqiaLzQmVcPgiCy.json?token=asdasd")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":1,"acknowledged":1,"acknowledged_at":1665561600,"acknowledged_by":"a1213qd","acknowledged_by_device":"phone","last_delivered_at":1665561500,"expired":0,"expires_at":1665565100,"called_back":0,"called_back_at":0,"request":"1e0ad4da-a8cf-4a4c-9a64-58cba5a9c3b2"}"#)
+            .create();
+
+        let result = p.check_receipt("rLqVuqTRh62UzxtmqiaLzQmVcPgiCy").await;
+        m.assert();
+        assert_eq!(
+            result.unwrap(),
+            ReceiptStatus {
+                acknowledged_by: Some("a1213qd (phone)".to_string()),
+                acknowledged_at: Some(Utc.with_ymd_and_hms(2022, 10, 12, 8, 0, 0).unwrap()),
+                expired: false,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_receipt_pending() {
+        let p = make_pushover();
+
+        let m = mock("GET", "/receipts/rLqVuqTRh62UzxtmqiaLzQmVcPgiCy.json?token=asdasd")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":1,"acknowledged":0,"acknowledged_at":0,"acknowledged_by":"","acknowledged_by_device":"","last_delivered_at":1665561500,"expired":0,"expires_at":1665565100,"called_back":0,"called_back_at":0,"request":"1e0ad4da-a8cf-4a4c-9a64-58cba5a9c3b2"}"#)
+            .create();
+
+        let result = p.check_receipt("rLqVuqTRh62UzxtmqiaLzQmVcPgiCy").await;
+        m.assert();
+        assert_eq!(result.unwrap(), ReceiptStatus::default());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_receipt() {
+        let p = make_pushover();
+
+        let m = mock(
+            "POST",
+            "/receipts/rLqVuqTRh62UzxtmqiaLzQmVcPgiCy/cancel.json",
+        )
+        .match_body("token=asdasd")
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"status":1,"request":"1e0ad4da-a8cf-4a4c-9a64-58cba5a9c3b2"}"#)
+        .create();
+
+        let result = p.cancel_receipt("rLqVuqTRh62UzxtmqiaLzQmVcPgiCy").await;
+        m.assert();
+        assert!(result.is_ok());
+    }
 }
@@ -0,0 +1,197 @@
+use crate::{alert::Alert, client, settings::Settings};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::Channel;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SignalSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// URL of the signal-cli-rest-api instance.
+    pub api_url: String,
+    /// Registered number the alerts are sent from.
+    pub number: String,
+    /// Phone numbers, with country code, the alerts are sent to.
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    /// IDs of groups the alerts are sent to, as listed by `/v1/groups/<number>`.
+    #[serde(default)]
+    pub group_ids: Vec<String>,
+    /// Whether to put the key figures in bold.
+    #[serde(default)]
+    pub styled: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct SignalErrorResponse {
+    error: String,
+}
+
+/// Sends alerts as Signal messages through a signal-cli-rest-api instance.
+pub struct Signal {
+    settings: SignalSettings,
+    http_client: Client,
+}
+
+impl Signal {
+    pub fn with_settings(settings: SignalSettings) -> Signal {
+        let http_client = client::get_self_hosted_http_client().unwrap();
+
+        Signal {
+            settings,
+            http_client,
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for Signal {
+    fn new(settings: &Settings) -> Signal {
+        Signal::with_settings(settings.signal.clone())
+    }
+
+    fn name(&self) -> &str {
+        "Signal"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let message = if self.settings.styled {
+            alert.markdown_message()
+        } else {
+            alert.message()
+        };
+        let recipients: Vec<&String> = self
+            .settings
+            .recipients
+            .iter()
+            .chain(self.settings.group_ids.iter())
+            .collect();
+        let body = json!({
+            "number": self.settings.number,
+            "recipients": recipients,
+            "message": format!("{}\n{message}", alert.title()),
+            "text_mode": if self.settings.styled { "styled" } else { "normal" },
+        });
+
+        let url = format!("{}/v2/send", self.settings.api_url.trim_end_matches('/'));
+        let resp = self
+            .http_client
+            .post(url.as_str())
+            .json(&body)
+            .send()
+            .await?;
+
+        if resp.status().is_success() {
+            Ok(None)
+        } else {
+            let status = resp.status();
+            match resp.json::<SignalErrorResponse>().await {
+                Ok(resp) => Err(anyhow!("failed sending alert to Signal: {}", resp.error)),
+                Err(_) => Err(anyhow!("failed sending alert to Signal: {status}")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use mockito::{mock, Matcher};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{Signal, SignalSettings};
+
+    fn make_signal() -> Signal {
+        let settings = SignalSettings {
+            enabled: true,
+            api_url: format!("{}/signal/", mockito::server_url()),
+            number: "+254700000000".to_string(),
+            recipients: vec!["+254712345678".to_string()],
+            group_ids: vec!["group.ZmFtaWx5".to_string()],
+            styled: false,
+        };
+
+        Signal::with_settings(settings)
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_successfully() {
+        let s = make_signal();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/signal/v2/send")
+            .match_body(Matcher::Json(json!({
+                "number": "+254700000000",
+                "recipients": ["+254712345678", "group.ZmFtaWx5"],
+                "message": "KPLC Bill (#1234567): 10 - October 2022\n\
+                            Balance of KES 3592.34 is due on 25 October, 2022!",
+                "text_mode": "normal",
+            })))
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body("{\"timestamp\":\"1666245600000\"}")
+            .create();
+
+        let result = s
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_styled_alert() {
+        let mut s = make_signal();
+        s.settings.styled = true;
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/signal/v2/send")
+            .match_body(Matcher::PartialJson(json!({
+                "message": "KPLC Bill (#1234567): 10 - October 2022\n\
+                            Balance of **KES 3592.34** is due on **25 October, 2022**!",
+                "text_mode": "styled",
+            })))
+            .with_status(201)
+            .create();
+
+        let result = s
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_error() {
+        let s = make_signal();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/signal/v2/send")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body("{\"error\":\"Invalid group id\"}")
+            .create();
+
+        let result = s
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "failed sending alert to Signal: Invalid group id"
+        );
+    }
+}
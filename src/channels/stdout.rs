@@ -0,0 +1,46 @@
+use std::io::{self, Write};
+
+use crate::{alert::Alert, settings::Settings};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+
+use super::{line, Channel};
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct StdoutSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Template each alert is written with, instead of as JSON.
+    pub template: Option<String>,
+}
+
+/// Writes each alert as a line on standard output.
+pub struct Stdout {
+    settings: StdoutSettings,
+}
+
+#[async_trait]
+impl Channel for Stdout {
+    fn new(settings: &Settings) -> Stdout {
+        Stdout {
+            settings: settings.stdout.clone(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let line = line::render(alert, self.settings.template.as_deref(), Utc::now());
+        writeln!(io::stdout().lock(), "{line}")?;
+
+        Ok(None)
+    }
+}
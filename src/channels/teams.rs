@@ -0,0 +1,215 @@
+use crate::{
+    alert::{Alert, Severity},
+    client,
+    settings::Settings,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::Channel;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct TeamsSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// URL of the channel's incoming webhook (or workflow).
+    pub webhook_url: String,
+}
+
+/// Posts alerts to a Microsoft Teams channel as Adaptive Cards.
+pub struct Teams {
+    settings: TeamsSettings,
+    http_client: Client,
+}
+
+impl Teams {
+    pub fn with_settings(settings: TeamsSettings) -> Teams {
+        let http_client = client::get_http_client().unwrap();
+
+        Teams {
+            settings,
+            http_client,
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for Teams {
+    fn new(settings: &Settings) -> Teams {
+        Teams::with_settings(settings.teams.clone())
+    }
+
+    fn name(&self) -> &str {
+        "Teams"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let resp = self
+            .http_client
+            .post(self.settings.webhook_url.as_str())
+            .json(&card(alert))
+            .send()
+            .await?;
+
+        if resp.status().is_success() {
+            Ok(None)
+        } else {
+            Err(anyhow!(
+                "failed sending alert to Teams: {} {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            ))
+        }
+    }
+}
+
+fn card(alert: &Alert<'_>) -> Value {
+    let color = match alert.severity() {
+        Severity::Low => "Good",
+        Severity::Normal => "Default",
+        Severity::High => "Warning",
+        Severity::Critical => "Attention",
+    };
+    let facts: Vec<Value> = alert
+        .facts()
+        .into_iter()
+        .map(|(title, value)| json!({"title": title, "value": value}))
+        .collect();
+
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "body": [
+                    {
+                        "type": "TextBlock",
+                        "text": alert.title(),
+                        "size": "Medium",
+                        "weight": "Bolder",
+                        "color": color,
+                        "wrap": true,
+                    },
+                    {
+                        "type": "TextBlock",
+                        // Adaptive Cards only break lines on blank lines
+                        "text": alert.markdown_message().replace('\n', "\n\n"),
+                        "wrap": true,
+                    },
+                    {"type": "FactSet", "facts": facts},
+                ],
+            },
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use mockito::{mock, Matcher};
+    use pretty_assertions::assert_eq;
+    use reqwest::Client;
+    use serde_json::json;
+
+    use super::{Teams, TeamsSettings};
+
+    fn make_teams() -> Teams {
+        let settings = TeamsSettings {
+            enabled: true,
+            webhook_url: format!("{}/teams", mockito::server_url()),
+        };
+
+        Teams {
+            settings,
+            http_client: Client::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_successfully() {
+        let t = make_teams();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/teams")
+            .match_body(Matcher::Json(json!({
+                "type": "message",
+                "attachments": [{
+                    "contentType": "application/vnd.microsoft.card.adaptive",
+                    "content": {
+                        "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                        "type": "AdaptiveCard",
+                        "version": "1.4",
+                        "body": [
+                            {
+                                "type": "TextBlock",
+                                "text": "KPLC Bill (#1234567): 10 - October 2022",
+                                "size": "Medium",
+                                "weight": "Bolder",
+                                "color": "Attention",
+                                "wrap": true,
+                            },
+                            {
+                                "type": "TextBlock",
+                                "text": "Balance of **KES 3592.34** is due on **25 October, 2022**!",
+                                "wrap": true,
+                            },
+                            {
+                                "type": "FactSet",
+                                "facts": [
+                                    {"title": "Account", "value": "1234567"},
+                                    {"title": "Balance", "value": "KES 3592.34 in arrears"},
+                                    {"title": "Due date", "value": "25 October, 2022"},
+                                    {"title": "Latest bill", "value": "10 - October 2022"},
+                                    {"title": "Bill amount", "value": "KES 3593"},
+                                ],
+                            },
+                        ],
+                    },
+                }],
+            })))
+            .with_status(200)
+            .with_body("1")
+            .create();
+
+        let result = t
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_error() {
+        let t = make_teams();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/teams")
+            .with_status(400)
+            .with_body("Bad payload received by generic incoming webhook.")
+            .create();
+
+        let result = t
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "failed sending alert to Teams: 400 Bad Request \
+             Bad payload received by generic incoming webhook."
+        );
+    }
+}
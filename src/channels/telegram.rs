@@ -0,0 +1,222 @@
+use crate::{alert::Alert, client, dashboard, settings::Settings};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::Channel;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TelegramSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    /// Token of the bot the alerts are sent as, as given by @BotFather.
+    pub bot_token: String,
+    /// IDs of the chats, groups or channels the alerts are sent to.
+    pub chat_ids: Vec<String>,
+}
+
+impl Default for TelegramSettings {
+    fn default() -> Self {
+        TelegramSettings {
+            enabled: false,
+            api_url: default_api_url(),
+            bot_token: String::new(),
+            chat_ids: vec![],
+        }
+    }
+}
+
+fn default_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+struct TelegramErrorResponse {
+    description: String,
+}
+
+/// Sends alerts as Telegram messages from a bot.
+pub struct Telegram {
+    settings: TelegramSettings,
+    http_client: Client,
+}
+
+impl Telegram {
+    pub fn with_settings(settings: TelegramSettings) -> Telegram {
+        let http_client = client::get_http_client().unwrap();
+
+        Telegram {
+            settings,
+            http_client,
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for Telegram {
+    fn new(settings: &Settings) -> Telegram {
+        Telegram::with_settings(settings.telegram.clone())
+    }
+
+    fn name(&self) -> &str {
+        "Telegram"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let url = format!(
+            "{}/bot{}/sendMessage",
+            self.settings.api_url.trim_end_matches('/'),
+            self.settings.bot_token
+        );
+        let text = format!(
+            "<b>{}</b>\n{}",
+            dashboard::escape(&alert.title()),
+            alert.html_message()
+        );
+
+        let mut errors = vec![];
+        for chat_id in self.settings.chat_ids.iter() {
+            let body = json!({
+                "chat_id": chat_id,
+                "text": text,
+                "parse_mode": "HTML",
+            });
+
+            let resp = self
+                .http_client
+                .post(url.as_str())
+                .json(&body)
+                .send()
+                .await
+                // the error would include the URL, and with it the bot token
+                .map_err(|err| {
+                    anyhow!("failed sending alert to Telegram: {}", err.without_url())
+                })?;
+            if resp.status().is_success() {
+                continue;
+            }
+
+            let status = resp.status();
+            match resp.json::<TelegramErrorResponse>().await {
+                Ok(resp) => errors.push(format!("{chat_id}: {}", resp.description)),
+                Err(_) => errors.push(format!("{chat_id}: {status}")),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(None)
+        } else {
+            Err(anyhow!(
+                "failed sending alert to Telegram: {}",
+                errors.join(", ")
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use mockito::{mock, Matcher};
+    use pretty_assertions::assert_eq;
+    use reqwest::Client;
+    use serde_json::json;
+
+    use super::{Telegram, TelegramSettings};
+
+    fn make_telegram() -> Telegram {
+        let settings = TelegramSettings {
+            enabled: true,
+            api_url: mockito::server_url(),
+            bot_token: "123456789:abcdefg".to_string(),
+            chat_ids: vec!["-100123456".to_string()],
+        };
+
+        Telegram {
+            settings,
+            http_client: Client::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_successfully() {
+        let t = make_telegram();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/bot123456789:abcdefg/sendMessage")
+            .match_body(Matcher::Json(json!({
+                "chat_id": "-100123456",
+                "text": "<b>KPLC Bill (#1234567): 10 - October 2022</b>\n\
+                         Balance of <b>KES 3592.34</b> is due on <b>25 October, 2022</b>!",
+                "parse_mode": "HTML",
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"ok\":true,\"result\":{\"message_id\":42}}")
+            .create();
+
+        let result = t
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_escapes_title() {
+        let t = make_telegram();
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        bill.data.col_bills[0].billing_period = "<10 - October 2022>".to_string();
+
+        let m = mock("POST", "/bot123456789:abcdefg/sendMessage")
+            .match_body(Matcher::PartialJson(json!({
+                "text": "<b>KPLC Bill (#1234567): &lt;10 - October 2022&gt;</b>\n\
+                         Balance of <b>KES 3592.34</b> is due on <b>25 October, 2022</b>!",
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"ok\":true,\"result\":{\"message_id\":42}}")
+            .create();
+
+        let result = t
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_error() {
+        let t = make_telegram();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/bot123456789:abcdefg/sendMessage")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(
+                "{\"ok\":false,\"error_code\":400,\"description\":\"Bad Request: chat not found\"}",
+            )
+            .create();
+
+        let result = t
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "failed sending alert to Telegram: -100123456: Bad Request: chat not found"
+        );
+    }
+}
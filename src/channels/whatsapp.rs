@@ -0,0 +1,310 @@
+use crate::{alert::Alert, client, credit::Balance, settings::Settings};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::Channel;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct WhatsAppSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_graph_url")]
+    pub graph_url: String,
+    #[serde(default = "default_api_version")]
+    pub api_version: String,
+    /// ID of the business phone number the messages are sent from.
+    pub phone_number_id: String,
+    pub access_token: String,
+    /// Phone numbers, with country code, the alerts are sent to.
+    pub recipients: Vec<String>,
+    /// Name of the approved message template alerts are sent with.
+    pub template: String,
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// What fills the template's body parameters, in order.
+    #[serde(default = "default_parameters")]
+    pub parameters: Vec<TemplateParameter>,
+}
+
+impl Default for WhatsAppSettings {
+    fn default() -> Self {
+        WhatsAppSettings {
+            enabled: false,
+            graph_url: default_graph_url(),
+            api_version: default_api_version(),
+            phone_number_id: String::new(),
+            access_token: String::new(),
+            recipients: vec![],
+            template: String::new(),
+            language: default_language(),
+            parameters: default_parameters(),
+        }
+    }
+}
+
+fn default_graph_url() -> String {
+    "https://graph.facebook.com".to_string()
+}
+
+fn default_api_version() -> String {
+    "v19.0".to_string()
+}
+
+fn default_language() -> String {
+    "en".to_string()
+}
+
+fn default_parameters() -> Vec<TemplateParameter> {
+    vec![
+        TemplateParameter::Account,
+        TemplateParameter::Balance,
+        TemplateParameter::DueDate,
+    ]
+}
+
+/// A value from the alert that fills a template parameter.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateParameter {
+    Account,
+    Balance,
+    DueDate,
+    BillingPeriod,
+    BillAmount,
+    Title,
+    Message,
+}
+
+#[derive(Deserialize, Debug)]
+struct WhatsAppErrorResponse {
+    error: WhatsAppError,
+}
+
+#[derive(Deserialize, Debug)]
+struct WhatsAppError {
+    message: String,
+    code: i64,
+}
+
+/// Sends alerts as WhatsApp template messages through Meta's Cloud API.
+pub struct WhatsApp {
+    settings: WhatsAppSettings,
+    http_client: Client,
+}
+
+impl WhatsApp {
+    pub fn with_settings(settings: WhatsAppSettings) -> WhatsApp {
+        // Meta's API is only ever reached over HTTPS, plain HTTP is left to
+        // endpoints configured in its place, such as a local proxy
+        let http_client = if settings.graph_url == default_graph_url() {
+            client::get_http_client().unwrap()
+        } else {
+            client::get_self_hosted_http_client().unwrap()
+        };
+
+        WhatsApp {
+            settings,
+            http_client,
+        }
+    }
+}
+
+#[async_trait]
+impl Channel for WhatsApp {
+    fn new(settings: &Settings) -> WhatsApp {
+        WhatsApp::with_settings(settings.whatsapp.clone())
+    }
+
+    fn name(&self) -> &str {
+        "WhatsApp"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    async fn send_alert(&self, alert: &Alert<'_>) -> Result<Option<String>> {
+        let url = format!(
+            "{}/{}/{}/messages",
+            self.settings.graph_url.trim_end_matches('/'),
+            self.settings.api_version,
+            self.settings.phone_number_id
+        );
+        let parameters: Vec<Value> = self
+            .settings
+            .parameters
+            .iter()
+            .map(|parameter| json!({"type": "text", "text": parameter_text(alert, *parameter)}))
+            .collect();
+
+        let mut errors = vec![];
+        for recipient in self.settings.recipients.iter() {
+            let body = json!({
+                "messaging_product": "whatsapp",
+                "to": recipient,
+                "type": "template",
+                "template": {
+                    "name": self.settings.template,
+                    "language": {"code": self.settings.language},
+                    "components": [{"type": "body", "parameters": parameters}],
+                },
+            });
+
+            let resp = self
+                .http_client
+                .post(url.as_str())
+                .bearer_auth(self.settings.access_token.as_str())
+                .json(&body)
+                .send()
+                .await?;
+            if resp.status().is_success() {
+                continue;
+            }
+
+            let status = resp.status();
+            match resp.json::<WhatsAppErrorResponse>().await {
+                Ok(resp) => errors.push(format!(
+                    "{recipient}: {} ({})",
+                    resp.error.message, resp.error.code
+                )),
+                Err(_) => errors.push(format!("{recipient}: {status}")),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(None)
+        } else {
+            Err(anyhow!(
+                "failed sending alert to WhatsApp: {}",
+                errors.join(", ")
+            ))
+        }
+    }
+}
+
+fn parameter_text(alert: &Alert<'_>, parameter: TemplateParameter) -> String {
+    let bill = alert.bill;
+    let latest_bill = bill.data.col_bills.first();
+
+    let text = match parameter {
+        TemplateParameter::Account => bill.data.account_reference.clone(),
+        TemplateParameter::Balance => Balance::of(bill.data.balance).to_string(),
+        // the oldest unpaid bill is the first to fall due
+        TemplateParameter::DueDate => match bill.unpaid_bills().last() {
+            Some(unpaid_bill) if bill.data.balance.is_sign_negative() => {
+                unpaid_bill.due_date.format("%d %B, %Y").to_string()
+            }
+            _ => "nothing due".to_string(),
+        },
+        TemplateParameter::BillingPeriod => latest_bill
+            .map(|latest_bill| latest_bill.billing_period.clone())
+            .unwrap_or_default(),
+        TemplateParameter::BillAmount => latest_bill
+            .map(|latest_bill| format!("KES {}", latest_bill.bill_amount))
+            .unwrap_or_default(),
+        TemplateParameter::Title => alert.title(),
+        TemplateParameter::Message => alert.message(),
+    };
+
+    // parameters can't contain new lines
+    text.replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::Channel,
+        test_utils::get_kplc_bill_resp,
+    };
+    use mockito::{mock, Matcher};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{WhatsApp, WhatsAppSettings};
+
+    fn make_whatsapp() -> WhatsApp {
+        let settings = WhatsAppSettings {
+            enabled: true,
+            graph_url: mockito::server_url(),
+            phone_number_id: "106540352242922".to_string(),
+            access_token: "EAAJB".to_string(),
+            recipients: vec!["254712345678".to_string()],
+            template: "kplc_bill_due".to_string(),
+            ..Default::default()
+        };
+
+        WhatsApp::with_settings(settings)
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_successfully() {
+        let w = make_whatsapp();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/v19.0/106540352242922/messages")
+            .match_header("authorization", "Bearer EAAJB")
+            .match_body(Matcher::Json(json!({
+                "messaging_product": "whatsapp",
+                "to": "254712345678",
+                "type": "template",
+                "template": {
+                    "name": "kplc_bill_due",
+                    "language": {"code": "en"},
+                    "components": [{
+                        "type": "body",
+                        "parameters": [
+                            {"type": "text", "text": "1234567"},
+                            {"type": "text", "text": "KES 3592.34 in arrears"},
+                            {"type": "text", "text": "25 October, 2022"},
+                        ],
+                    }],
+                },
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                "{\"messaging_product\":\"whatsapp\",\
+                 \"contacts\":[{\"input\":\"254712345678\",\"wa_id\":\"254712345678\"}],\
+                 \"messages\":[{\"id\":\"wamid.HBgLMjU0NzEyMzQ1Njc4\"}]}",
+            )
+            .create();
+
+        let result = w
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_alert_error() {
+        let mut w = make_whatsapp();
+        w.settings.recipients.push("254787654321".to_string());
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let m = mock("POST", "/v19.0/106540352242922/messages")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(
+                "{\"error\":{\"message\":\"(#132001) Template name does not exist in the \
+                 translation\",\"type\":\"OAuthException\",\"code\":132001}}",
+            )
+            .expect(2)
+            .create();
+
+        let result = w
+            .send_alert(&Alert::new(AlertKind::BalanceDue, &bill))
+            .await;
+        m.assert();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "failed sending alert to WhatsApp: \
+             254712345678: (#132001) Template name does not exist in the translation (132001), \
+             254787654321: (#132001) Template name does not exist in the translation (132001)"
+        );
+    }
+}
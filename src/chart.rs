@@ -0,0 +1,126 @@
+use anyhow::{anyhow, Result};
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::kplc::KPLCBillColBills;
+
+// number of most recent bills plotted on the chart
+const MAX_BILLS: usize = 12;
+
+const WIDTH: u32 = 600;
+const HEIGHT: u32 = 300;
+const MARGIN: u32 = 20;
+
+type Rgb = [u8; 3];
+
+const BACKGROUND: Rgb = [255, 255, 255];
+const AXIS: Rgb = [90, 90, 90];
+const AVERAGE: Rgb = [190, 190, 190];
+const BAR: Rgb = [70, 130, 180];
+const LATEST_BAR: Rgb = [230, 126, 34];
+
+struct Canvas {
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Canvas {
+        Canvas {
+            pixels: BACKGROUND.repeat((WIDTH * HEIGHT) as usize),
+        }
+    }
+
+    fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, colour: Rgb) {
+        for row in y..(y + height).min(HEIGHT) {
+            for col in x..(x + width).min(WIDTH) {
+                let offset = ((row * WIDTH + col) * 3) as usize;
+                self.pixels[offset..offset + 3].copy_from_slice(&colour);
+            }
+        }
+    }
+
+    fn encode(self) -> Result<Vec<u8>> {
+        let mut png = vec![];
+        let mut encoder = png::Encoder::new(&mut png, WIDTH, HEIGHT);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+
+        Ok(png)
+    }
+}
+
+/// Renders a PNG bar chart of the most recent bill amounts, oldest on the
+/// left, with the latest bill highlighted and a line marking the average.
+pub fn bill_amounts_png(col_bills: &[KPLCBillColBills]) -> Result<Vec<u8>> {
+    // bills come newest first
+    let amounts: Vec<f64> = col_bills
+        .iter()
+        .take(MAX_BILLS)
+        .rev()
+        .map(|bill| bill.bill_amount.to_f64().unwrap_or_default().max(0.0))
+        .collect();
+
+    let max_amount = amounts.iter().cloned().fold(0.0, f64::max);
+    if max_amount <= 0.0 {
+        return Err(anyhow!("no bill amounts to chart"));
+    }
+
+    let plot_height = HEIGHT - 2 * MARGIN;
+    let baseline = HEIGHT - MARGIN;
+    let slot_width = (WIDTH - 2 * MARGIN) / amounts.len() as u32;
+    let bar_width = slot_width * 7 / 10;
+    let scale = |amount: f64| (amount / max_amount * plot_height as f64).round() as u32;
+
+    let mut canvas = Canvas::new();
+
+    for (i, amount) in amounts.iter().enumerate() {
+        let bar_height = scale(*amount);
+        let x = MARGIN + i as u32 * slot_width + (slot_width - bar_width) / 2;
+        let colour = if i == amounts.len() - 1 {
+            LATEST_BAR
+        } else {
+            BAR
+        };
+
+        canvas.fill_rect(x, baseline - bar_height, bar_width, bar_height, colour);
+    }
+
+    let average = amounts.iter().sum::<f64>() / amounts.len() as f64;
+    canvas.fill_rect(
+        MARGIN,
+        baseline - scale(average),
+        WIDTH - 2 * MARGIN,
+        1,
+        AVERAGE,
+    );
+    canvas.fill_rect(MARGIN, baseline, WIDTH - 2 * MARGIN, 2, AXIS);
+
+    canvas.encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{bill_amounts_png, HEIGHT, WIDTH};
+    use crate::test_utils::get_kplc_bill_resp;
+
+    #[test]
+    fn test_bill_amounts_png() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+
+        let png = bill_amounts_png(&bill.data.col_bills).unwrap();
+
+        let decoder = png::Decoder::new(png.as_slice());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().width, WIDTH);
+        assert_eq!(reader.info().height, HEIGHT);
+    }
+
+    #[test]
+    fn test_bill_amounts_png_without_bills() {
+        let result = bill_amounts_png(&[]);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "no bill amounts to chart");
+    }
+}
@@ -1,10 +1,10 @@
 use anyhow::Result;
-use reqwest::{header, Client};
+use reqwest::{header, Client, ClientBuilder};
 
 // user agent to use to make requests
 static USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
-pub fn get_http_client() -> Result<Client> {
+fn get_http_client_builder() -> ClientBuilder {
     let mut default_headers = header::HeaderMap::new();
     default_headers.insert(header::ACCEPT, header::HeaderValue::from_static("*/*"));
     default_headers.insert(
@@ -12,10 +12,19 @@ pub fn get_http_client() -> Result<Client> {
         header::HeaderValue::from_static("keep-alive"),
     );
 
-    let client = Client::builder()
+    Client::builder()
         .user_agent(USER_AGENT)
         .default_headers(default_headers)
-        .https_only(true)
-        .build()?;
+}
+
+pub fn get_http_client() -> Result<Client> {
+    let client = get_http_client_builder().https_only(true).build()?;
+    Ok(client)
+}
+
+/// Client for endpoints the user configures, such as self-hosted servers on
+/// the local network, which may well be plain HTTP.
+pub fn get_self_hosted_http_client() -> Result<Client> {
+    let client = get_http_client_builder().build()?;
     Ok(client)
 }
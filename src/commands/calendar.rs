@@ -0,0 +1,21 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+
+use crate::{calendar, settings::Settings, state::State};
+
+/// Prints an iCalendar of the due dates of the account's stored bills.
+pub fn run(settings: &Settings, account_number: &str) -> Result<()> {
+    let mut state = State::load(&settings.state.path).context("error loading state")?;
+
+    print!(
+        "{}",
+        calendar::render(
+            account_number,
+            &state.account(account_number).bills,
+            &settings.calendar,
+            Utc::now()
+        )
+    );
+
+    Ok(())
+}
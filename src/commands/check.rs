@@ -0,0 +1,516 @@
+use std::{fmt, mem};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, warn};
+
+use crate::{
+    alert::{Alert, AlertKind, Severity},
+    anomaly, budget,
+    channels::{self, Channel, ReceiptStatus},
+    credit::{self, Balance},
+    estimate,
+    kplc::KPLCBillQuery,
+    metrics::Metrics,
+    payment,
+    settings::Settings,
+    state::{AccountState, Delivery, DeliveryStatus, Receipt, State},
+    tariff::{self, Tariffs},
+};
+
+/// Fetches the account's bill, records it in the history and sends any alerts due.
+pub async fn run(settings: &Settings, account_number: &str, metrics: &Metrics) -> Result<()> {
+    let kplc_settings = settings.kplc.clone();
+    let kplc_query = KPLCBillQuery::new(kplc_settings);
+    info!("fetching bill from KPLC");
+    let bill = match kplc_query.get_bill(account_number).await {
+        Ok(bill) => bill,
+        Err(err) => {
+            metrics.record_fetch_failure(account_number);
+            return Err(err).context("error fetching bill from KPLC");
+        }
+    };
+    metrics.record_fetch(account_number, &bill, Utc::now());
+    info!("done fetching bill from KPLC");
+
+    debug!("loading state from file {}", settings.state.path);
+    let mut state = State::load(&settings.state.path).context("error loading state")?;
+    let account_state = state.account(account_number);
+    let payment = payment::detect(account_state, &bill, Utc::now());
+    account_state.record(&bill);
+
+    let account_settings = settings.account(account_number);
+    let channels = channels::get_channels(settings);
+    let is_paid = !bill.data.balance.is_sign_negative();
+
+    check_receipts(&channels, account_state, is_paid).await;
+
+    for channel in channels.iter().filter(|channel| channel.is_enabled()) {
+        if let Err(err) = channel.publish_status(&bill).await {
+            error!("error publishing status to {}: {}", channel.name(), err);
+        }
+    }
+
+    let mut failed = false;
+    if let Some(payment) = payment {
+        info!(
+            "payment of KES {} received... sending alert to enabled channels",
+            payment.amount
+        );
+        let alert = Alert::new(AlertKind::PaymentReceived(payment), &bill);
+        failed |= !send_alert(&channels, &alert, account_state, metrics).await;
+    }
+
+    if !is_paid {
+        info!("balance present");
+        let mut notes: Vec<String> = account_state
+            .latest_consumption()
+            .iter()
+            .map(|period| period.summary())
+            .collect();
+        notes.extend(budget::projection_summary(
+            account_state,
+            account_settings.budget,
+        ));
+        let alert = Alert::new(AlertKind::BalanceDue, &bill).with_notes(notes);
+        // sent once per bill, and again once it's overdue
+        failed |= match account_state.bills.first() {
+            Some(latest_bill) => {
+                let bill_number = latest_bill.bill_number.clone();
+                let marker = if alert.severity() == Severity::Critical {
+                    Marker::Bill(
+                        |account_state| &mut account_state.last_overdue_bill,
+                        bill_number,
+                    )
+                } else {
+                    Marker::Bill(
+                        |account_state| &mut account_state.last_balance_due_bill,
+                        bill_number,
+                    )
+                };
+                !send_alert_once(&channels, &alert, account_state, metrics, marker).await
+            }
+            None => !send_alert(&channels, &alert, account_state, metrics).await,
+        };
+    } else if let Balance::Credit(amount) = Balance::of(bill.data.balance) {
+        info!("KES {} in credit", amount);
+    } else {
+        info!("no balance present");
+    }
+
+    if let Some(credit_settings) = &settings.credit {
+        match credit::check(account_state, credit_settings) {
+            Some(shortfall) => {
+                let bill_number = shortfall.bill_number.clone();
+                let alert = Alert::new(AlertKind::LowCredit(shortfall), &bill);
+                failed |= !send_alert_once(
+                    &channels,
+                    &alert,
+                    account_state,
+                    metrics,
+                    Marker::Bill(
+                        |account_state| &mut account_state.last_low_credit_bill,
+                        bill_number,
+                    ),
+                )
+                .await;
+            }
+            None => debug!("no credit shortfall"),
+        }
+    }
+
+    if let Some(anomaly_settings) = &settings.anomaly {
+        match anomaly::detect(account_state, anomaly_settings) {
+            Some(anomaly) => {
+                let bill_number = anomaly.bill_number.clone();
+                let alert = Alert::new(AlertKind::UnusualBill(anomaly), &bill);
+                failed |= !send_alert_once(
+                    &channels,
+                    &alert,
+                    account_state,
+                    metrics,
+                    Marker::Bill(
+                        |account_state| &mut account_state.last_unusual_bill,
+                        bill_number,
+                    ),
+                )
+                .await;
+            }
+            None => debug!("no unusual bill"),
+        }
+    }
+
+    if let Some(estimate_settings) = &settings.estimated_reading {
+        for estimated_reading in estimate::detect(&account_state.readings, estimate_settings) {
+            let serial_num = estimated_reading.serial_num.clone();
+            let reading_date = estimated_reading.reading_date;
+            let alert = Alert::new(AlertKind::EstimatedReading(estimated_reading), &bill);
+            failed |= !send_alert_once(
+                &channels,
+                &alert,
+                account_state,
+                metrics,
+                Marker::Reading(serial_num, reading_date),
+            )
+            .await;
+        }
+    }
+
+    // reported once the state is saved, so receipts of alerts already sent aren't lost
+    let mut tariffs_error = None;
+    if let Some(tariff_settings) = &settings.tariff {
+        match Tariffs::load(tariff_settings.path.as_deref()) {
+            Ok(tariffs) => match tariff::verify(account_state, &tariffs, tariff_settings) {
+                Some(discrepancy) => {
+                    let bill_number = discrepancy.bill_number.clone();
+                    let alert = Alert::new(AlertKind::BillDiscrepancy(discrepancy), &bill);
+                    failed |= !send_alert_once(
+                        &channels,
+                        &alert,
+                        account_state,
+                        metrics,
+                        Marker::Bill(
+                            |account_state| &mut account_state.last_discrepant_bill,
+                            bill_number,
+                        ),
+                    )
+                    .await;
+                }
+                None => debug!("bill matches the tariff"),
+            },
+            Err(err) => tariffs_error = Some(err.context("error loading tariffs")),
+        }
+    }
+
+    if let Some(budget) = account_settings.budget {
+        match budget::check(account_state, budget) {
+            Some(overrun) => {
+                let bill_number = overrun.bill_number.clone();
+                let alert = Alert::new(AlertKind::OverBudget(overrun), &bill);
+                failed |= !send_alert_once(
+                    &channels,
+                    &alert,
+                    account_state,
+                    metrics,
+                    Marker::Bill(
+                        |account_state| &mut account_state.last_over_budget_bill,
+                        bill_number,
+                    ),
+                )
+                .await;
+            }
+            None => debug!("within budget"),
+        }
+    }
+
+    state
+        .save(&settings.state.path)
+        .context("error saving state")?;
+
+    if let Some(err) = tariffs_error {
+        return Err(err);
+    }
+
+    if failed {
+        Err(AlertsFailed.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// The bill was fetched and recorded, but some channels failed to send alerts.
+#[derive(Debug)]
+pub struct AlertsFailed;
+
+impl fmt::Display for AlertsFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error sending alerts")
+    }
+}
+
+impl std::error::Error for AlertsFailed {}
+
+/// Sends the alert to every enabled channel, keeping track of any receipts.
+/// Returns `false` if any of the channels failed.
+async fn send_alert(
+    channels: &[Box<dyn Channel>],
+    alert: &Alert<'_>,
+    account_state: &mut AccountState,
+    metrics: &Metrics,
+) -> bool {
+    let mut sent = true;
+
+    let channel_ids = channels::channel_ids(channels);
+    for (channel, channel_id) in channels.iter().zip(channel_ids.iter()) {
+        if !channel.is_enabled() {
+            continue;
+        }
+
+        info!("sending alert to {}", channel_id);
+        let result = channel.send_alert(alert).await;
+        metrics.record_alert(channel_id, result.is_ok());
+        let mut delivery = Delivery {
+            channel: channel_id.clone(),
+            title: alert.title(),
+            sent_at: Utc::now(),
+            status: DeliveryStatus::Sent,
+            receipt: None,
+        };
+        match result {
+            Ok(receipt) => {
+                info!("sent alert to {}", channel_id);
+                if let Some(id) = receipt {
+                    account_state.receipts.push(Receipt {
+                        channel: channel_id.clone(),
+                        id: id.clone(),
+                        sent_at: delivery.sent_at,
+                    });
+                    delivery.status = DeliveryStatus::AwaitingAcknowledgement;
+                    delivery.receipt = Some(id);
+                }
+            }
+            Err(err) => {
+                error!("error sending alert to {}: {}", channel_id, err);
+                delivery.status = DeliveryStatus::Failed;
+                sent = false;
+            }
+        };
+        account_state.record_delivery(delivery);
+    }
+
+    sent
+}
+
+/// Where the account state records that an alert was sent, so it's only sent once.
+enum Marker {
+    /// The bill number an alert was last sent for.
+    Bill(fn(&mut AccountState) -> &mut Option<String>, String),
+    /// A meter's serial number and the date of the reading an alert was last sent for.
+    Reading(String, DateTime<Utc>),
+}
+
+impl Marker {
+    fn is_set(&self, account_state: &mut AccountState) -> bool {
+        match self {
+            Marker::Bill(marker, bill_number) => {
+                marker(account_state).as_ref() == Some(bill_number)
+            }
+            Marker::Reading(serial_num, reading_date) => {
+                account_state.last_estimated_reading.get(serial_num) == Some(reading_date)
+            }
+        }
+    }
+
+    fn set(self, account_state: &mut AccountState) {
+        match self {
+            Marker::Bill(marker, bill_number) => *marker(account_state) = Some(bill_number),
+            Marker::Reading(serial_num, reading_date) => {
+                account_state
+                    .last_estimated_reading
+                    .insert(serial_num, reading_date);
+            }
+        }
+    }
+}
+
+/// Sends the alert unless the marker shows it was already sent, setting the
+/// marker once it is. Returns `false` if any of the channels failed.
+async fn send_alert_once(
+    channels: &[Box<dyn Channel>],
+    alert: &Alert<'_>,
+    account_state: &mut AccountState,
+    metrics: &Metrics,
+    marker: Marker,
+) -> bool {
+    if marker.is_set(account_state) {
+        debug!("already sent alert: {}", alert.title());
+        return true;
+    }
+
+    info!("{}... sending alert to enabled channels", alert.title());
+    if !send_alert(channels, alert, account_state, metrics).await {
+        return false;
+    }
+    marker.set(account_state);
+    true
+}
+
+/// Follows up on alerts awaiting acknowledgement, cancelling them once the bill is paid.
+async fn check_receipts(
+    channels: &[Box<dyn Channel>],
+    account_state: &mut AccountState,
+    is_paid: bool,
+) {
+    let mut outstanding = vec![];
+
+    let channel_ids = channels::channel_ids(channels);
+    for receipt in mem::take(&mut account_state.receipts) {
+        let channel = match channels
+            .iter()
+            .zip(channel_ids.iter())
+            .find(|(_, channel_id)| **channel_id == receipt.channel)
+        {
+            Some((channel, _)) => channel,
+            None => {
+                warn!(
+                    "dropping receipt {} from unknown channel {}",
+                    receipt.id, receipt.channel
+                );
+                continue;
+            }
+        };
+
+        if is_paid {
+            match channel.cancel_receipt(&receipt.id).await {
+                Ok(_) => {
+                    info!(
+                        "bill paid, cancelled alert {} on {}",
+                        receipt.id, receipt.channel
+                    );
+                    account_state.update_delivery(&receipt.id, DeliveryStatus::Cancelled);
+                }
+                Err(err) => {
+                    error!("error cancelling alert {}: {}", receipt.id, err);
+                    outstanding.push(receipt);
+                }
+            }
+            continue;
+        }
+
+        match channel.check_receipt(&receipt.id).await {
+            Ok(ReceiptStatus {
+                acknowledged_by: Some(acknowledged_by),
+                acknowledged_at,
+                ..
+            }) => {
+                info!(
+                    "alert {} on {} acknowledged by {} at {}",
+                    receipt.id,
+                    receipt.channel,
+                    acknowledged_by,
+                    acknowledged_at.unwrap_or(receipt.sent_at)
+                );
+                account_state.update_delivery(&receipt.id, DeliveryStatus::Acknowledged);
+            }
+            Ok(status) if status.expired => {
+                warn!(
+                    "alert {} on {} expired without being acknowledged",
+                    receipt.id, receipt.channel
+                );
+                account_state.update_delivery(&receipt.id, DeliveryStatus::Expired);
+            }
+            Ok(_) => {
+                info!(
+                    "alert {} on {} not yet acknowledged",
+                    receipt.id, receipt.channel
+                );
+                outstanding.push(receipt);
+            }
+            Err(err) => {
+                error!("error checking alert {}: {}", receipt.id, err);
+                outstanding.push(receipt);
+            }
+        }
+    }
+
+    account_state.receipts = outstanding;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use pretty_assertions::assert_eq;
+
+    use super::{check_receipts, send_alert};
+    use crate::{
+        alert::{Alert, AlertKind},
+        channels::{Channel, ReceiptStatus},
+        metrics::Metrics,
+        settings::Settings,
+        state::AccountState,
+        test_utils::get_kplc_bill_resp,
+    };
+
+    /// Stands in for a Pushover channel with its own app token, keeping
+    /// track of the receipts checked on it.
+    struct FakePushover {
+        token: &'static str,
+        checked: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Channel for FakePushover {
+        fn new(_settings: &Settings) -> FakePushover {
+            FakePushover {
+                token: "",
+                checked: Arc::default(),
+            }
+        }
+
+        fn name(&self) -> &str {
+            "Pushover"
+        }
+
+        fn is_enabled(&self) -> bool {
+            true
+        }
+
+        async fn send_alert(&self, _alert: &Alert<'_>) -> Result<Option<String>> {
+            Ok(Some(format!("receipt-{}", self.token)))
+        }
+
+        async fn check_receipt(&self, receipt: &str) -> Result<ReceiptStatus> {
+            self.checked
+                .lock()
+                .unwrap()
+                .push(format!("{receipt} with {}", self.token));
+            Ok(ReceiptStatus::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_receipts_checked_on_issuing_channel() {
+        let checked = Arc::new(Mutex::new(vec![]));
+        // e.g. the `[pushover]` section and a `pover://` URL
+        let channels: Vec<Box<dyn Channel>> = vec![
+            Box::new(FakePushover {
+                token: "section-token",
+                checked: checked.clone(),
+            }),
+            Box::new(FakePushover {
+                token: "url-token",
+                checked: checked.clone(),
+            }),
+        ];
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let mut account_state = AccountState::default();
+
+        let sent = send_alert(
+            &channels,
+            &Alert::new(AlertKind::BalanceDue, &bill),
+            &mut account_state,
+            &Metrics::default(),
+        )
+        .await;
+        assert!(sent);
+        let receipt_channels: Vec<_> = account_state
+            .receipts
+            .iter()
+            .map(|receipt| receipt.channel.as_str())
+            .collect();
+        assert_eq!(receipt_channels, vec!["Pushover", "Pushover #2"]);
+
+        check_receipts(&channels, &mut account_state, false).await;
+        assert_eq!(
+            *checked.lock().unwrap(),
+            vec![
+                "receipt-section-token with section-token",
+                "receipt-url-token with url-token",
+            ]
+        );
+        assert_eq!(account_state.receipts.len(), 2);
+    }
+}
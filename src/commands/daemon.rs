@@ -0,0 +1,105 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use log::{error, info};
+use serde::{de, Deserialize, Deserializer};
+use tokio::sync::Mutex;
+
+use crate::{
+    commands::check,
+    metrics::Metrics,
+    server::{self, AppState},
+    settings::Settings,
+};
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DaemonSettings {
+    /// Address the HTTP endpoints are served on.
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    /// How often, in minutes, bills are checked.
+    #[serde(
+        default = "default_interval_minutes",
+        deserialize_with = "deserialize_interval_minutes"
+    )]
+    pub interval_minutes: u64,
+    /// Whether to serve the JSON API for bill status.
+    #[serde(default)]
+    pub api: bool,
+    /// Whether to serve the HTML dashboard.
+    #[serde(default)]
+    pub dashboard: bool,
+}
+
+impl Default for DaemonSettings {
+    fn default() -> Self {
+        DaemonSettings {
+            listen: default_listen(),
+            interval_minutes: default_interval_minutes(),
+            api: false,
+            dashboard: false,
+        }
+    }
+}
+
+fn default_listen() -> String {
+    "127.0.0.1:9184".to_string()
+}
+
+fn default_interval_minutes() -> u64 {
+    360
+}
+
+// an interval of zero would have the daemon check bills in a busy loop
+fn deserialize_interval_minutes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u64, D::Error> {
+    let interval_minutes = u64::deserialize(deserializer)?;
+    if interval_minutes == 0 {
+        return Err(de::Error::custom("interval_minutes must be at least 1"));
+    }
+
+    Ok(interval_minutes)
+}
+
+/// Checks the account, along with any others configured under `[accounts]`,
+/// on an interval while serving metrics, and the API and dashboard if
+/// enabled, over HTTP.
+pub async fn run(settings: Settings, account_number: &str) -> Result<()> {
+    let mut account_numbers: Vec<String> = settings.accounts.keys().cloned().collect();
+    account_numbers.push(account_number.to_string());
+    account_numbers.sort_unstable();
+    account_numbers.dedup();
+
+    let state = AppState {
+        settings: Arc::new(settings),
+        metrics: Arc::new(Metrics::default()),
+        account_numbers: Arc::new(account_numbers),
+        check_lock: Arc::new(Mutex::new(())),
+        refreshed_at: Arc::new(Mutex::new(HashMap::new())),
+    };
+
+    let checks = async {
+        let interval_minutes = state.settings.daemon.interval_minutes;
+        let interval = Duration::from_secs(interval_minutes * 60);
+
+        loop {
+            for account_number in state.account_numbers.iter() {
+                let _guard = state.check_lock.lock().await;
+                info!("checking account {}", account_number);
+                if let Err(err) = check::run(&state.settings, account_number, &state.metrics).await
+                {
+                    error!("error checking account {}: {:#}", account_number, err);
+                }
+            }
+
+            info!("next check in {} minutes", interval_minutes);
+            tokio::time::sleep(interval).await;
+        }
+    };
+
+    tokio::select! {
+        result = server::serve(&state.settings.daemon.listen, state.clone()) => result,
+        _ = checks => Ok(()),
+    }
+}
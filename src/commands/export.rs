@@ -0,0 +1,168 @@
+use std::io::{self, Write};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+
+use crate::{settings::Settings, state::AccountState, state::State};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Records {
+    Bills,
+    Readings,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub format: Format,
+    pub records: Records,
+    /// First and last dates, inclusive, of the bills (by end of billing period)
+    /// or readings to export.
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// Writes the account's stored bills or meter readings to stdout, oldest first.
+pub fn run(settings: &Settings, account_number: &str, options: &ExportOptions) -> Result<()> {
+    let mut state = State::load(&settings.state.path).context("error loading state")?;
+
+    export(state.account(account_number), options, io::stdout().lock())
+        .context("error exporting history")
+}
+
+fn export(account_state: &AccountState, options: &ExportOptions, writer: impl Write) -> Result<()> {
+    let in_range = |date: &DateTime<Utc>| {
+        let date = date.date_naive();
+        options.from.is_none_or(|from| date >= from) && options.to.is_none_or(|to| date <= to)
+    };
+
+    match options.records {
+        Records::Bills => write_records(
+            account_state
+                .bills
+                .iter()
+                .rev()
+                .filter(|bill| in_range(&bill.to_date)),
+            options.format,
+            writer,
+        ),
+        Records::Readings => write_records(
+            account_state
+                .readings
+                .iter()
+                .rev()
+                .filter(|reading| in_range(&reading.reading_date)),
+            options.format,
+            writer,
+        ),
+    }
+}
+
+fn write_records<T: Serialize>(
+    records: impl Iterator<Item = T>,
+    format: Format,
+    mut writer: impl Write,
+) -> Result<()> {
+    match format {
+        Format::Csv => {
+            let mut csv_writer = csv::Writer::from_writer(writer);
+            for record in records {
+                csv_writer.serialize(record)?;
+            }
+            csv_writer.flush()?;
+        }
+        Format::Jsonl => {
+            for record in records {
+                serde_json::to_writer(&mut writer, &record)?;
+                writeln!(writer)?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::{export, ExportOptions, Format, Records};
+    use crate::state::{AccountState, BillRecord, MeterReading};
+
+    fn make_account_state() -> AccountState {
+        let bills = (9..=11)
+            .rev()
+            .map(|month| BillRecord {
+                bill_number: format!("98123912321{month}"),
+                billing_period: format!("{month} - 2022"),
+                from_date: Utc.with_ymd_and_hms(2022, month - 1, 4, 21, 0, 0).unwrap(),
+                to_date: Utc.with_ymd_and_hms(2022, month, 4, 21, 0, 0).unwrap(),
+                due_date: Utc.with_ymd_and_hms(2022, month, 25, 21, 0, 0).unwrap(),
+                bill_amount: Decimal::new(359234, 2),
+                bill_pend_amount: Decimal::ZERO,
+            })
+            .collect();
+
+        AccountState {
+            bills,
+            readings: vec![MeterReading {
+                serial_num: "981928391283".to_string(),
+                reading_date: Utc.with_ymd_and_hms(2022, 10, 4, 21, 0, 0).unwrap(),
+                reading_value: 18234,
+                estimated: false,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn export_to_string(options: &ExportOptions) -> String {
+        let mut out = vec![];
+        export(&make_account_state(), options, &mut out).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_export_bills_csv() {
+        let options = ExportOptions {
+            format: Format::Csv,
+            records: Records::Bills,
+            from: NaiveDate::from_ymd_opt(2022, 10, 1),
+            to: None,
+        };
+
+        assert_eq!(
+            export_to_string(&options),
+            "bill_number,billing_period,from_date,to_date,due_date,bill_amount,bill_pend_amount\n\
+             9812391232110,10 - 2022,2022-09-04T21:00:00Z,2022-10-04T21:00:00Z,\
+             2022-10-25T21:00:00Z,3592.34,0\n\
+             9812391232111,11 - 2022,2022-10-04T21:00:00Z,2022-11-04T21:00:00Z,\
+             2022-11-25T21:00:00Z,3592.34,0\n"
+        );
+    }
+
+    #[test]
+    fn test_export_readings_jsonl() {
+        let options = ExportOptions {
+            format: Format::Jsonl,
+            records: Records::Readings,
+            from: None,
+            to: NaiveDate::from_ymd_opt(2022, 12, 31),
+        };
+
+        assert_eq!(
+            export_to_string(&options),
+            "{\"serial_num\":\"981928391283\",\"reading_date\":\"2022-10-04T21:00:00Z\",\
+             \"reading_value\":18234,\"estimated\":false}\n"
+        );
+    }
+}
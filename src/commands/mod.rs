@@ -0,0 +1,6 @@
+pub mod calendar;
+pub mod check;
+pub mod daemon;
+pub mod export;
+pub mod show;
+pub mod stats;
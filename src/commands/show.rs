@@ -0,0 +1,177 @@
+use std::fmt::Write;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    budget,
+    credit::{self, Balance, CreditSettings},
+    settings::{AccountSettings, Settings},
+    state::{AccountState, State},
+};
+
+/// Prints the account's balance and latest bill as of the last check.
+pub fn run(settings: &Settings, account_number: &str) -> Result<()> {
+    let mut state = State::load(&settings.state.path).context("error loading state")?;
+
+    print!(
+        "{}",
+        format_account(
+            account_number,
+            state.account(account_number),
+            &settings.account(account_number),
+            &settings.credit.clone().unwrap_or_default()
+        )
+    );
+
+    Ok(())
+}
+
+fn format_account(
+    account_number: &str,
+    account_state: &AccountState,
+    account_settings: &AccountSettings,
+    credit_settings: &CreditSettings,
+) -> String {
+    let mut out = format!("Account {account_number}\n");
+
+    let balance = match account_state.balance {
+        Some(balance) => Balance::of(balance),
+        None => {
+            out.push_str("No bills yet: run a check first.\n");
+            return out;
+        }
+    };
+    writeln!(out, "Balance: {balance}").unwrap();
+
+    if let Some(latest_bill) = account_state.bills.first() {
+        writeln!(
+            out,
+            "Latest bill: {} ({}) of KES {}, due on {}",
+            latest_bill.bill_number,
+            latest_bill.billing_period,
+            latest_bill.bill_amount,
+            latest_bill.due_date.format("%d %B, %Y")
+        )
+        .unwrap();
+    }
+
+    if let Some(projection) = budget::project(account_state) {
+        write!(out, "Projected next bill: KES {projection}").unwrap();
+        if let Some(budget) = account_settings.budget {
+            write!(out, " (budget KES {budget})").unwrap();
+        }
+        out.push('\n');
+    }
+
+    if let Some(shortfall) = credit::check(account_state, credit_settings) {
+        writeln!(
+            out,
+            "Credit won't cover the next bill: KES {} short",
+            shortfall.shortfall()
+        )
+        .unwrap();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::format_account;
+    use crate::{
+        credit::CreditSettings,
+        settings::AccountSettings,
+        state::{AccountState, BillRecord},
+    };
+
+    fn make_account_state(balance: Decimal) -> AccountState {
+        AccountState {
+            balance: Some(balance),
+            bills: vec![BillRecord {
+                bill_number: "981239123213".to_string(),
+                billing_period: "10 - October 2022".to_string(),
+                from_date: Utc.with_ymd_and_hms(2022, 9, 4, 21, 0, 0).unwrap(),
+                to_date: Utc.with_ymd_and_hms(2022, 10, 4, 21, 0, 0).unwrap(),
+                due_date: Utc.with_ymd_and_hms(2022, 10, 25, 21, 0, 0).unwrap(),
+                bill_amount: Decimal::new(3000, 0),
+                bill_pend_amount: Decimal::ZERO,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_format_account_in_arrears() {
+        let account_settings = AccountSettings {
+            budget: Some(Decimal::new(4000, 0)),
+        };
+
+        assert_eq!(
+            format_account(
+                "1234567",
+                &make_account_state(Decimal::new(-3000, 0)),
+                &account_settings,
+                &CreditSettings::default()
+            ),
+            "Account 1234567\n\
+             Balance: KES 3000 in arrears\n\
+             Latest bill: 981239123213 (10 - October 2022) of KES 3000, due on 25 October, 2022\n\
+             Projected next bill: KES 3000 (budget KES 4000)\n"
+        );
+    }
+
+    #[test]
+    fn test_format_account_in_credit() {
+        assert_eq!(
+            format_account(
+                "1234567",
+                &make_account_state(Decimal::new(500, 0)),
+                &AccountSettings::default(),
+                &CreditSettings::default()
+            ),
+            "Account 1234567\n\
+             Balance: KES 500 in credit\n\
+             Latest bill: 981239123213 (10 - October 2022) of KES 3000, due on 25 October, 2022\n\
+             Projected next bill: KES 3000\n\
+             Credit won't cover the next bill: KES 2500 short\n"
+        );
+    }
+
+    #[test]
+    fn test_format_account_in_credit_below_cover_percent() {
+        let credit_settings = CreditSettings {
+            cover_percent: Decimal::new(10, 0),
+        };
+
+        assert_eq!(
+            format_account(
+                "1234567",
+                &make_account_state(Decimal::new(500, 0)),
+                &AccountSettings::default(),
+                &credit_settings
+            ),
+            "Account 1234567\n\
+             Balance: KES 500 in credit\n\
+             Latest bill: 981239123213 (10 - October 2022) of KES 3000, due on 25 October, 2022\n\
+             Projected next bill: KES 3000\n"
+        );
+    }
+
+    #[test]
+    fn test_format_account_without_bills() {
+        assert_eq!(
+            format_account(
+                "1234567",
+                &AccountState::default(),
+                &AccountSettings::default(),
+                &CreditSettings::default()
+            ),
+            "Account 1234567\n\
+             No bills yet: run a check first.\n"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt::Write;
+
+use anyhow::{Context, Result};
+
+use crate::{consumption, settings::Settings, state::AccountState, state::State};
+
+/// Prints consumption worked out from the meter readings in the account's history.
+pub fn run(settings: &Settings, account_number: &str) -> Result<()> {
+    let mut state = State::load(&settings.state.path).context("error loading state")?;
+
+    print!(
+        "{}",
+        format_stats(account_number, state.account(account_number))
+    );
+
+    Ok(())
+}
+
+fn format_stats(account_number: &str, account_state: &AccountState) -> String {
+    let mut out = format!("Account {account_number}\n");
+
+    if account_state.consumption.is_empty() {
+        out.push_str("No consumption yet: readings from at least two billing cycles are needed.\n");
+        return out;
+    }
+
+    let mut serial_nums: Vec<&str> = account_state
+        .consumption
+        .iter()
+        .map(|period| period.serial_num.as_str())
+        .collect();
+    serial_nums.dedup();
+
+    for serial_num in serial_nums {
+        let periods: Vec<_> = account_state
+            .consumption
+            .iter()
+            .filter(|period| period.serial_num == serial_num)
+            .cloned()
+            .collect();
+
+        writeln!(out, "\nMeter {serial_num}").unwrap();
+        writeln!(
+            out,
+            "{:<12}{:<12}{:>8}{:>10}{:>16}{:>10}",
+            "From", "To", "kWh", "kWh/day", "Bill", "KES/kWh"
+        )
+        .unwrap();
+
+        for period in periods.iter() {
+            let cost_per_kwh = period
+                .cost_per_kwh
+                .map(|cost| cost.to_string())
+                .unwrap_or_else(|| "-".to_string());
+
+            writeln!(
+                out,
+                "{:<12}{:<12}{:>8}{:>10.1}{:>16}{:>10}",
+                period.from_date.format("%Y-%m-%d").to_string(),
+                period.to_date.format("%Y-%m-%d").to_string(),
+                period.kwh,
+                period.daily_kwh,
+                period.bill_number.as_deref().unwrap_or("-"),
+                cost_per_kwh
+            )
+            .unwrap();
+        }
+
+        if let Some(daily_kwh) = consumption::average_daily_kwh(&periods) {
+            writeln!(out, "Average daily consumption: {daily_kwh:.1} kWh").unwrap();
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::format_stats;
+    use crate::{consumption::ConsumptionPeriod, state::AccountState};
+
+    #[test]
+    fn test_format_stats() {
+        let account_state = AccountState {
+            consumption: vec![ConsumptionPeriod {
+                serial_num: "981928391283".to_string(),
+                from_date: Utc.with_ymd_and_hms(2022, 9, 6, 21, 0, 0).unwrap(),
+                to_date: Utc.with_ymd_and_hms(2022, 10, 4, 21, 0, 0).unwrap(),
+                kwh: 523,
+                daily_kwh: 523.0 / 28.0,
+                bill_number: Some("981239123213".to_string()),
+                cost_per_kwh: Some(Decimal::new(687, 2)),
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format_stats("1234567", &account_state),
+            "Account 1234567\n\
+             \n\
+             Meter 981928391283\n\
+             From        To               kWh   kWh/day            Bill   KES/kWh\n\
+             2022-09-06  2022-10-04       523      18.7    981239123213      6.87\n\
+             Average daily consumption: 18.7 kWh\n"
+        );
+    }
+
+    #[test]
+    fn test_format_stats_without_consumption() {
+        assert_eq!(
+            format_stats("1234567", &AccountState::default()),
+            "Account 1234567\n\
+             No consumption yet: readings from at least two billing cycles are needed.\n"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use chrono::prelude::{DateTime, Utc};
+use chrono::Duration;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::state::{BillRecord, MeterReading};
+
+/// Electricity used on a meter between two consecutive readings.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConsumptionPeriod {
+    pub serial_num: String,
+    pub from_date: DateTime<Utc>,
+    pub to_date: DateTime<Utc>,
+    pub kwh: usize,
+    pub daily_kwh: f64,
+    /// The bill covering this period, if one was issued off the closing reading.
+    pub bill_number: Option<String>,
+    pub cost_per_kwh: Option<Decimal>,
+}
+
+impl ConsumptionPeriod {
+    pub fn days(&self) -> i64 {
+        (self.to_date - self.from_date).num_days().max(1)
+    }
+
+    /// One line summary, as included in alerts.
+    pub fn summary(&self) -> String {
+        let kwh = self.kwh;
+        let days = self.days();
+        let daily_kwh = self.daily_kwh;
+        let summary = format!("Used {kwh} kWh over {days} days ({daily_kwh:.1} kWh/day)");
+
+        match self.cost_per_kwh {
+            Some(cost_per_kwh) => format!("{summary}, about KES {cost_per_kwh}/kWh."),
+            None => format!("{summary}."),
+        }
+    }
+}
+
+// bills are issued off the meter reading taken on their closing date
+fn bill_for_reading(bills: &[BillRecord], reading_date: DateTime<Utc>) -> Option<&BillRecord> {
+    bills.iter().find(|bill| {
+        (bill.to_date - reading_date).num_seconds().abs() <= Duration::days(1).num_seconds()
+    })
+}
+
+/// Works out consumption between consecutive readings of every meter, along
+/// with the cost per kWh of the bills issued off those readings.
+pub fn compute(readings: &[MeterReading], bills: &[BillRecord]) -> Vec<ConsumptionPeriod> {
+    let mut readings: Vec<&MeterReading> = readings.iter().collect();
+    readings.sort_by(|a, b| {
+        (a.serial_num.as_str(), a.reading_date).cmp(&(b.serial_num.as_str(), b.reading_date))
+    });
+
+    let mut periods = vec![];
+
+    for pair in readings.windows(2) {
+        let (previous, current) = (pair[0], pair[1]);
+
+        // a lower reading means the meter was replaced or rolled over
+        if previous.serial_num != current.serial_num
+            || current.reading_value < previous.reading_value
+        {
+            continue;
+        }
+
+        let kwh = current.reading_value - previous.reading_value;
+        let days = (current.reading_date - previous.reading_date)
+            .num_days()
+            .max(1);
+        let bill = bill_for_reading(bills, current.reading_date);
+        let cost_per_kwh = match bill {
+            Some(bill) if kwh > 0 => Some((bill.bill_amount / Decimal::from(kwh)).round_dp(2)),
+            _ => None,
+        };
+
+        periods.push(ConsumptionPeriod {
+            serial_num: current.serial_num.clone(),
+            from_date: previous.reading_date,
+            to_date: current.reading_date,
+            kwh,
+            daily_kwh: kwh as f64 / days as f64,
+            bill_number: bill.map(|bill| bill.bill_number.clone()),
+            cost_per_kwh,
+        });
+    }
+
+    periods
+}
+
+/// Average daily consumption across the given periods, weighted by their length.
+pub fn average_daily_kwh(periods: &[ConsumptionPeriod]) -> Option<f64> {
+    let days: i64 = periods.iter().map(|period| period.days()).sum();
+    let kwh: usize = periods.iter().map(|period| period.kwh).sum();
+
+    if days == 0 {
+        None
+    } else {
+        Some(kwh as f64 / days as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::{average_daily_kwh, compute, ConsumptionPeriod};
+    use crate::state::{BillRecord, MeterReading};
+
+    fn make_reading(serial_num: &str, day: u32, month: u32, value: usize) -> MeterReading {
+        MeterReading {
+            serial_num: serial_num.to_string(),
+            reading_date: Utc.with_ymd_and_hms(2022, month, day, 21, 0, 0).unwrap(),
+            reading_value: value,
+            estimated: false,
+        }
+    }
+
+    fn make_bill() -> BillRecord {
+        BillRecord {
+            bill_number: "981239123213".to_string(),
+            billing_period: "10 - October 2022".to_string(),
+            from_date: Utc.with_ymd_and_hms(2022, 9, 7, 21, 0, 0).unwrap(),
+            to_date: Utc.with_ymd_and_hms(2022, 10, 4, 21, 0, 0).unwrap(),
+            due_date: Utc.with_ymd_and_hms(2022, 10, 25, 21, 0, 0).unwrap(),
+            bill_amount: Decimal::new(3593, 0),
+            bill_pend_amount: Decimal::new(359234, 2),
+        }
+    }
+
+    #[test]
+    fn test_compute_consumption() {
+        let readings = vec![
+            make_reading("981928391283", 4, 10, 18234),
+            make_reading("981928391283", 6, 9, 17711),
+        ];
+
+        let periods = compute(&readings, &[make_bill()]);
+        assert_eq!(
+            periods,
+            vec![ConsumptionPeriod {
+                serial_num: "981928391283".to_string(),
+                from_date: Utc.with_ymd_and_hms(2022, 9, 6, 21, 0, 0).unwrap(),
+                to_date: Utc.with_ymd_and_hms(2022, 10, 4, 21, 0, 0).unwrap(),
+                kwh: 523,
+                daily_kwh: 523.0 / 28.0,
+                bill_number: Some("981239123213".to_string()),
+                cost_per_kwh: Some(Decimal::new(687, 2)),
+            }]
+        );
+        assert_eq!(
+            periods[0].summary(),
+            "Used 523 kWh over 28 days (18.7 kWh/day), about KES 6.87/kWh."
+        );
+    }
+
+    #[test]
+    fn test_compute_consumption_skips_meter_replacement() {
+        let readings = vec![
+            make_reading("981928391283", 6, 9, 17711),
+            make_reading("981928391283", 4, 10, 12),
+            make_reading("111111111111", 4, 10, 12),
+        ];
+
+        assert_eq!(compute(&readings, &[]), vec![]);
+    }
+
+    #[test]
+    fn test_average_daily_kwh() {
+        let readings = vec![
+            make_reading("981928391283", 6, 8, 17100),
+            make_reading("981928391283", 6, 9, 17711),
+            make_reading("981928391283", 4, 10, 18234),
+        ];
+        let periods = compute(&readings, &[]);
+
+        assert_eq!(average_daily_kwh(&periods), Some(1134.0 / 59.0));
+        assert_eq!(average_daily_kwh(&[]), None);
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::{budget, state::AccountState};
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CreditSettings {
+    /// Alert when credit covers less than this percentage of the projected bill.
+    #[serde(default = "default_cover_percent")]
+    pub cover_percent: Decimal,
+}
+
+impl Default for CreditSettings {
+    fn default() -> Self {
+        CreditSettings {
+            cover_percent: default_cover_percent(),
+        }
+    }
+}
+
+fn default_cover_percent() -> Decimal {
+    Decimal::ONE_HUNDRED
+}
+
+/// An account balance as reported by KPLC, where a negative balance is owed
+/// and a positive one is credit from overpaying.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Balance {
+    Arrears(Decimal),
+    Credit(Decimal),
+    Settled,
+}
+
+impl Balance {
+    pub fn of(balance: Decimal) -> Balance {
+        if balance.is_zero() {
+            Balance::Settled
+        } else if balance.is_sign_negative() {
+            Balance::Arrears(balance.abs())
+        } else {
+            Balance::Credit(balance)
+        }
+    }
+}
+
+impl fmt::Display for Balance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Balance::Arrears(amount) => write!(f, "KES {amount} in arrears"),
+            Balance::Credit(amount) => write!(f, "KES {amount} in credit"),
+            Balance::Settled => write!(f, "settled"),
+        }
+    }
+}
+
+/// Credit on the account that won't cover the next bill.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreditShortfall {
+    pub bill_number: String,
+    pub credit: Decimal,
+    pub projected_amount: Decimal,
+}
+
+impl CreditShortfall {
+    pub fn shortfall(&self) -> Decimal {
+        self.projected_amount - self.credit
+    }
+}
+
+/// Checks credit left on the account against the projected next bill.
+pub fn check(account_state: &AccountState, settings: &CreditSettings) -> Option<CreditShortfall> {
+    let credit = match Balance::of(account_state.balance?) {
+        Balance::Credit(credit) => credit,
+        _ => return None,
+    };
+    let latest_bill = account_state.bills.first()?;
+    let projected_amount = budget::project(account_state)?;
+
+    if credit >= projected_amount * settings.cover_percent / Decimal::ONE_HUNDRED {
+        return None;
+    }
+
+    Some(CreditShortfall {
+        bill_number: latest_bill.bill_number.clone(),
+        credit,
+        projected_amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::{check, Balance, CreditSettings, CreditShortfall};
+    use crate::state::{AccountState, BillRecord};
+
+    fn make_account_state(balance: Decimal) -> AccountState {
+        AccountState {
+            balance: Some(balance),
+            bills: vec![BillRecord {
+                bill_number: "981239123213".to_string(),
+                billing_period: "10 - October 2022".to_string(),
+                from_date: Utc.with_ymd_and_hms(2022, 9, 4, 21, 0, 0).unwrap(),
+                to_date: Utc.with_ymd_and_hms(2022, 10, 4, 21, 0, 0).unwrap(),
+                due_date: Utc.with_ymd_and_hms(2022, 10, 25, 21, 0, 0).unwrap(),
+                bill_amount: Decimal::new(3000, 0),
+                bill_pend_amount: Decimal::ZERO,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn make_settings() -> CreditSettings {
+        CreditSettings {
+            cover_percent: Decimal::ONE_HUNDRED,
+        }
+    }
+
+    #[test]
+    fn test_balance() {
+        assert_eq!(
+            Balance::of(Decimal::new(-359234, 2)).to_string(),
+            "KES 3592.34 in arrears"
+        );
+        assert_eq!(
+            Balance::of(Decimal::new(500, 0)).to_string(),
+            "KES 500 in credit"
+        );
+        assert_eq!(Balance::of(Decimal::ZERO), Balance::Settled);
+    }
+
+    #[test]
+    fn test_check_credit_shortfall() {
+        let account_state = make_account_state(Decimal::new(500, 0));
+
+        let shortfall = check(&account_state, &make_settings()).unwrap();
+        assert_eq!(
+            shortfall,
+            CreditShortfall {
+                bill_number: "981239123213".to_string(),
+                credit: Decimal::new(500, 0),
+                projected_amount: Decimal::new(3000, 0),
+            }
+        );
+        assert_eq!(shortfall.shortfall(), Decimal::new(2500, 0));
+    }
+
+    #[test]
+    fn test_check_credit_covers_bill() {
+        let settings = make_settings();
+
+        assert_eq!(
+            check(&make_account_state(Decimal::new(3500, 0)), &settings),
+            None
+        );
+        assert_eq!(
+            check(&make_account_state(Decimal::new(-500, 0)), &settings),
+            None
+        );
+    }
+}
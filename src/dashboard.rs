@@ -0,0 +1,344 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use chrono::prelude::{DateTime, NaiveDate, Utc};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::{
+    credit::Balance,
+    state::{AccountState, DeliveryStatus},
+};
+
+// number of most recent bills charted
+const CHART_BILLS: usize = 12;
+const CHART_WIDTH: usize = 600;
+const CHART_HEIGHT: usize = 200;
+
+static STYLE: &str =
+    "body{font-family:sans-serif;max-width:680px;margin:auto;padding:1em;color:#222}\
+section{border-bottom:1px solid #ddd;padding-bottom:1em}\
+.due{font-size:1.4em}.overdue{color:#c0392b}\
+table{border-collapse:collapse;width:100%}td,th{text-align:left;padding:.25em .5em}\
+td.num,th.num{text-align:right}rect{fill:#2e86c1}text{font-size:11px}";
+
+/// Renders the dashboard page for the given accounts.
+pub fn render(accounts: &[(String, AccountState)], now: DateTime<Utc>) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>KPLC Bills</title><style>{STYLE}</style></head><body>\n<h1>KPLC Bills</h1>\n"
+    );
+
+    for (account_number, account_state) in accounts {
+        render_account(&mut out, account_number, account_state, now);
+    }
+
+    out.push_str("</body></html>\n");
+    out
+}
+
+fn render_account(
+    out: &mut String,
+    account_number: &str,
+    account_state: &AccountState,
+    now: DateTime<Utc>,
+) {
+    writeln!(
+        out,
+        "<section>\n<h2>Account {}</h2>",
+        escape(account_number)
+    )
+    .unwrap();
+
+    let balance = match account_state.balance {
+        Some(balance) => Balance::of(balance),
+        None => {
+            out.push_str("<p>No bills yet.</p>\n</section>\n");
+            return;
+        }
+    };
+    writeln!(out, "<p>Balance: <b>{balance}</b></p>").unwrap();
+
+    // the oldest unpaid bill is the first to fall due
+    let due_date = account_state
+        .bills
+        .iter()
+        .rev()
+        .find(|bill| bill.bill_pend_amount > Decimal::ZERO)
+        .map(|bill| bill.due_date);
+    match (balance, due_date) {
+        (Balance::Arrears(_), Some(due_date)) => {
+            let days = (due_date - now).num_days();
+            let date = due_date.format("%d %B, %Y");
+
+            if due_date < now {
+                writeln!(
+                    out,
+                    "<p class=\"due overdue\">Overdue by {} days (due {date})</p>",
+                    -days
+                )
+                .unwrap();
+            } else {
+                writeln!(out, "<p class=\"due\">Due in {days} days ({date})</p>").unwrap();
+            }
+        }
+        (Balance::Arrears(_), None) => out.push_str("<p class=\"due\">Payment due</p>\n"),
+        _ => out.push_str("<p class=\"due\">Nothing due</p>\n"),
+    }
+
+    render_chart(out, account_state);
+    render_consumption(out, account_state);
+    render_deliveries(out, account_state);
+
+    out.push_str("</section>\n");
+}
+
+fn render_chart(out: &mut String, account_state: &AccountState) {
+    // oldest first, left to right
+    let bills: Vec<_> = account_state.bills.iter().take(CHART_BILLS).rev().collect();
+    let max_amount = bills
+        .iter()
+        .filter_map(|bill| bill.bill_amount.to_f64())
+        .fold(0.0, f64::max);
+    if bills.is_empty() || max_amount <= 0.0 {
+        return;
+    }
+
+    writeln!(
+        out,
+        "<h3>Last {} bills</h3>\n<svg viewBox=\"0 0 {CHART_WIDTH} {}\" width=\"100%\" \
+         role=\"img\" aria-label=\"Bill amounts\">",
+        bills.len(),
+        CHART_HEIGHT + 20
+    )
+    .unwrap();
+
+    let slot = CHART_WIDTH / CHART_BILLS;
+    for (i, bill) in bills.iter().enumerate() {
+        let amount = bill.bill_amount.to_f64().unwrap_or_default().max(0.0);
+        let height = (amount / max_amount * (CHART_HEIGHT - 20) as f64).round() as usize;
+        let x = i * slot + slot / 8;
+        let y = CHART_HEIGHT - height;
+
+        writeln!(
+            out,
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{}\" height=\"{height}\">\
+             <title>{}: KES {}</title></rect>\
+             <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>\
+             <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            slot * 3 / 4,
+            escape(&bill.billing_period),
+            bill.bill_amount,
+            x + slot * 3 / 8,
+            y.saturating_sub(4),
+            bill.bill_amount.round(),
+            x + slot * 3 / 8,
+            CHART_HEIGHT + 14,
+            bill.to_date.format("%b %y"),
+        )
+        .unwrap();
+    }
+
+    out.push_str("</svg>\n");
+}
+
+fn render_consumption(out: &mut String, account_state: &AccountState) {
+    // periods rarely line up with calendar months, so each one's consumption
+    // is spread over the days it covers, summed across meters
+    let mut months: BTreeMap<String, (f64, BTreeSet<NaiveDate>)> = BTreeMap::new();
+    for period in account_state.consumption.iter() {
+        let days = period.days();
+        for day in period
+            .from_date
+            .date_naive()
+            .iter_days()
+            .take(days as usize)
+        {
+            let month = months.entry(day.format("%Y-%m").to_string()).or_default();
+            month.0 += period.kwh as f64 / days as f64;
+            month.1.insert(day);
+        }
+    }
+    if months.is_empty() {
+        return;
+    }
+
+    out.push_str(
+        "<h3>Consumption</h3>\n<table><tr><th>Month</th><th class=\"num\">kWh</th>\
+         <th class=\"num\">kWh/day</th></tr>\n",
+    );
+    for (month, (kwh, days)) in months.iter().rev() {
+        let daily_kwh = kwh / days.len() as f64;
+        writeln!(
+            out,
+            "<tr><td>{month}</td><td class=\"num\">{kwh:.0}</td>\
+             <td class=\"num\">{daily_kwh:.1}</td></tr>"
+        )
+        .unwrap();
+    }
+    out.push_str("</table>\n");
+}
+
+fn render_deliveries(out: &mut String, account_state: &AccountState) {
+    if account_state.deliveries.is_empty() {
+        return;
+    }
+
+    out.push_str(
+        "<h3>Recent alerts</h3>\n<table><tr><th>Sent</th><th>Channel</th><th>Alert</th>\
+         <th>Status</th></tr>\n",
+    );
+    for delivery in account_state.deliveries.iter() {
+        let status = match delivery.status {
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::AwaitingAcknowledgement => "awaiting acknowledgement",
+            DeliveryStatus::Acknowledged => "acknowledged",
+            DeliveryStatus::Expired => "expired",
+            DeliveryStatus::Cancelled => "cancelled",
+        };
+
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{status}</td></tr>",
+            delivery.sent_at.format("%d %b %H:%M"),
+            escape(&delivery.channel),
+            escape(&delivery.title),
+        )
+        .unwrap();
+    }
+    out.push_str("</table>\n");
+}
+
+/// Escapes text for inclusion in HTML.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    use super::render;
+    use crate::{
+        consumption,
+        state::{AccountState, BillRecord, Delivery, DeliveryStatus, MeterReading},
+    };
+
+    fn make_account_state() -> AccountState {
+        let bills = (9..=10)
+            .rev()
+            .map(|month| BillRecord {
+                bill_number: format!("98123912321{month}"),
+                billing_period: format!("{month} - 2022"),
+                from_date: Utc.with_ymd_and_hms(2022, month - 1, 4, 21, 0, 0).unwrap(),
+                to_date: Utc.with_ymd_and_hms(2022, month, 4, 21, 0, 0).unwrap(),
+                due_date: Utc.with_ymd_and_hms(2022, month, 25, 21, 0, 0).unwrap(),
+                bill_amount: Decimal::new(3000 + i64::from(month), 0),
+                bill_pend_amount: Decimal::new(3000 + i64::from(month), 0),
+            })
+            .collect();
+        let readings = vec![
+            MeterReading {
+                serial_num: "981928391283".to_string(),
+                reading_date: Utc.with_ymd_and_hms(2022, 10, 4, 21, 0, 0).unwrap(),
+                reading_value: 1300,
+                estimated: false,
+            },
+            MeterReading {
+                serial_num: "981928391283".to_string(),
+                reading_date: Utc.with_ymd_and_hms(2022, 9, 4, 21, 0, 0).unwrap(),
+                reading_value: 1000,
+                estimated: false,
+            },
+        ];
+
+        let mut account_state = AccountState {
+            balance: Some(Decimal::new(-6019, 0)),
+            bills,
+            readings,
+            ..Default::default()
+        };
+        account_state.consumption =
+            consumption::compute(&account_state.readings, &account_state.bills);
+        account_state.record_delivery(Delivery {
+            channel: "pushover".to_string(),
+            title: "KPLC Bill (#1234567): <10 - October 2022>".to_string(),
+            sent_at: Utc.with_ymd_and_hms(2022, 10, 5, 6, 0, 0).unwrap(),
+            status: DeliveryStatus::AwaitingAcknowledgement,
+            receipt: Some("receipt".to_string()),
+        });
+
+        account_state
+    }
+
+    #[test]
+    fn test_render_account() {
+        let accounts = vec![("1234567".to_string(), make_account_state())];
+        let now = Utc.with_ymd_and_hms(2022, 9, 20, 21, 0, 0).unwrap();
+
+        let html = render(&accounts, now);
+        assert!(html.contains("<h2>Account 1234567</h2>"));
+        assert!(html.contains("Balance: <b>KES 6019 in arrears</b>"));
+        // the September bill is due first
+        assert!(html.contains("Due in 5 days (25 September, 2022)"));
+        assert!(html.contains("<h3>Last 2 bills</h3>"));
+        assert!(html.contains("<title>9 - 2022: KES 3009</title>"));
+        // the 30 day period is split between September and October
+        assert!(html.contains(
+            "<tr><td>2022-10</td><td class=\"num\">30</td><td class=\"num\">10.0</td></tr>"
+        ));
+        assert!(html.contains(
+            "<tr><td>2022-09</td><td class=\"num\">270</td><td class=\"num\">10.0</td></tr>"
+        ));
+        assert!(html.contains(
+            "<td>KPLC Bill (#1234567): &lt;10 - October 2022&gt;</td>\
+             <td>awaiting acknowledgement</td>"
+        ));
+    }
+
+    #[test]
+    fn test_render_consumption_across_meters() {
+        let mut account_state = make_account_state();
+        let second_meter: Vec<_> = account_state
+            .readings
+            .iter()
+            .map(|reading| MeterReading {
+                serial_num: "981928391284".to_string(),
+                reading_value: reading.reading_value / 2,
+                ..reading.clone()
+            })
+            .collect();
+        account_state.readings.extend(second_meter);
+        account_state.consumption =
+            consumption::compute(&account_state.readings, &account_state.bills);
+
+        let html = render(&[("1234567".to_string(), account_state)], Utc::now());
+        assert!(html.contains(
+            "<tr><td>2022-09</td><td class=\"num\">405</td><td class=\"num\">15.0</td></tr>"
+        ));
+    }
+
+    #[test]
+    fn test_render_overdue_account() {
+        let accounts = vec![("1234567".to_string(), make_account_state())];
+        let now = Utc.with_ymd_and_hms(2022, 10, 1, 21, 0, 0).unwrap();
+
+        assert!(render(&accounts, now).contains("Overdue by 6 days (due 25 September, 2022)"));
+    }
+
+    #[test]
+    fn test_render_account_without_bills() {
+        let accounts = vec![("1234567".to_string(), AccountState::default())];
+
+        let html = render(&accounts, Utc::now());
+        assert!(html.contains("<p>No bills yet.</p>"));
+        assert!(!html.contains("<svg"));
+    }
+}
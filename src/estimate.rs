@@ -0,0 +1,138 @@
+use chrono::prelude::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::state::MeterReading;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EstimateSettings {
+    /// Number of consecutive identical readings after which a meter is
+    /// considered not to have been read.
+    #[serde(default = "default_unchanged_readings")]
+    pub unchanged_readings: usize,
+}
+
+fn default_unchanged_readings() -> usize {
+    3
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EstimateReason {
+    /// KPLC marked the reading as estimated.
+    Estimated,
+    /// The meter has shown the same value for this many readings.
+    Unchanged(usize),
+}
+
+/// A meter whose latest reading looks estimated rather than actually read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EstimatedReading {
+    pub serial_num: String,
+    pub reading_date: DateTime<Utc>,
+    pub reading_value: usize,
+    pub reason: EstimateReason,
+}
+
+/// Checks the latest reading of every meter in the reading history, which is
+/// ordered newest first.
+pub fn detect(readings: &[MeterReading], settings: &EstimateSettings) -> Vec<EstimatedReading> {
+    let mut serial_nums: Vec<&str> = readings
+        .iter()
+        .map(|reading| reading.serial_num.as_str())
+        .collect();
+    serial_nums.sort_unstable();
+    serial_nums.dedup();
+
+    let mut estimated_readings = vec![];
+
+    for serial_num in serial_nums {
+        let meter_readings: Vec<&MeterReading> = readings
+            .iter()
+            .filter(|reading| reading.serial_num == serial_num)
+            .collect();
+        let latest = meter_readings[0];
+
+        let unchanged = meter_readings
+            .iter()
+            .take_while(|reading| reading.reading_value == latest.reading_value)
+            .count();
+
+        let reason = if latest.estimated {
+            EstimateReason::Estimated
+        } else if settings.unchanged_readings > 1 && unchanged >= settings.unchanged_readings {
+            EstimateReason::Unchanged(unchanged)
+        } else {
+            continue;
+        };
+
+        estimated_readings.push(EstimatedReading {
+            serial_num: latest.serial_num.clone(),
+            reading_date: latest.reading_date,
+            reading_value: latest.reading_value,
+            reason,
+        });
+    }
+
+    estimated_readings
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::{detect, EstimateReason, EstimateSettings, EstimatedReading};
+    use crate::state::MeterReading;
+
+    fn make_reading(month: u32, value: usize, estimated: bool) -> MeterReading {
+        MeterReading {
+            serial_num: "981928391283".to_string(),
+            reading_date: Utc.with_ymd_and_hms(2022, month, 4, 21, 0, 0).unwrap(),
+            reading_value: value,
+            estimated,
+        }
+    }
+
+    fn make_settings() -> EstimateSettings {
+        EstimateSettings {
+            unchanged_readings: 3,
+        }
+    }
+
+    #[test]
+    fn test_detect_actual_readings() {
+        let readings = vec![make_reading(10, 18234, false), make_reading(9, 17711, true)];
+
+        assert_eq!(detect(&readings, &make_settings()), vec![]);
+    }
+
+    #[test]
+    fn test_detect_estimated_reading() {
+        let readings = vec![make_reading(10, 18234, true), make_reading(9, 17711, false)];
+
+        assert_eq!(
+            detect(&readings, &make_settings()),
+            vec![EstimatedReading {
+                serial_num: "981928391283".to_string(),
+                reading_date: Utc.with_ymd_and_hms(2022, 10, 4, 21, 0, 0).unwrap(),
+                reading_value: 18234,
+                reason: EstimateReason::Estimated,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_detect_unchanged_readings() {
+        let readings = vec![
+            make_reading(10, 17711, false),
+            make_reading(9, 17711, false),
+            make_reading(8, 17711, false),
+            make_reading(7, 17100, false),
+        ];
+
+        let estimated_readings = detect(&readings, &make_settings());
+        assert_eq!(estimated_readings.len(), 1);
+        assert_eq!(estimated_readings[0].reason, EstimateReason::Unchanged(3));
+
+        assert_eq!(detect(&readings[1..], &make_settings()), vec![]);
+    }
+}
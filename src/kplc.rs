@@ -2,7 +2,7 @@ use anyhow::{anyhow, Result};
 use chrono::prelude::{DateTime, Utc};
 use reqwest::{header, Client};
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::client;
@@ -16,14 +16,42 @@ pub struct KPLCSettings {
     pub token_scope: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct KPLCBill {
     pub data: KPLCBillData,
 }
 
-#[derive(Deserialize, Debug)]
+impl KPLCBill {
+    /// Bills with an amount still pending, newest first. When a balance is owed
+    /// but no bill has an amount pending, the latest bill is taken to be unpaid.
+    pub fn unpaid_bills(&self) -> Vec<&KPLCBillColBills> {
+        let unpaid_bills: Vec<_> = self
+            .data
+            .col_bills
+            .iter()
+            .filter(|col_bill| col_bill.bill_pend_amount > Decimal::ZERO)
+            .collect();
+
+        if unpaid_bills.is_empty() && self.data.balance.is_sign_negative() {
+            return self.data.col_bills.first().into_iter().collect();
+        }
+
+        unpaid_bills
+    }
+
+    /// Returns `true` if there's a balance owed and an unpaid bill's due date has passed.
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.data.balance.is_sign_negative()
+            && self
+                .unpaid_bills()
+                .iter()
+                .any(|col_bill| col_bill.due_date < now)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct KPLCBillData {
@@ -35,7 +63,7 @@ pub struct KPLCBillData {
     pub col_bills: Vec<KPLCBillColBills>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct KPLCBillMeterList {
@@ -43,16 +71,18 @@ pub struct KPLCBillMeterList {
     pub latest_usage_list: Vec<KPLCBillLatestUsage>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct KPLCBillLatestUsage {
     #[serde(with = "chrono::serde::ts_milliseconds")]
     pub reading_date: DateTime<Utc>,
     pub reading_value: usize,
+    #[serde(default)]
+    pub estimated: bool,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct KPLCBillColBills {
@@ -189,11 +219,13 @@ impl KPLCBillQuery {
 mod tests {
     use std::{env, fs};
 
+    use chrono::Utc;
     use mockito::mock;
     use pretty_assertions::assert_eq;
     use reqwest::Client;
+    use rust_decimal::Decimal;
 
-    use super::{KPLCBillQuery, KPLCSettings};
+    use super::{KPLCBill, KPLCBillQuery, KPLCSettings};
 
     fn make_kplc() -> KPLCBillQuery {
         let settings = KPLCSettings {
@@ -323,4 +355,21 @@ mod tests {
             "failed to get bill: The account number 12345 doesn´t exist."
         );
     }
+
+    #[test]
+    fn test_unpaid_bills() {
+        let mut bill: KPLCBill = serde_json::from_str(&get_body("kplc_bill_balance.json")).unwrap();
+
+        let unpaid_bills = bill.unpaid_bills();
+        assert_eq!(unpaid_bills.len(), 1);
+        assert_eq!(unpaid_bills[0].bill_number, "981239123213");
+
+        // owed, but no amount pending on any bill
+        bill.data.col_bills[0].bill_pend_amount = Decimal::ZERO;
+        assert_eq!(bill.unpaid_bills().len(), 1);
+
+        bill.data.col_bills.clear();
+        assert!(bill.unpaid_bills().is_empty());
+        assert!(!bill.is_overdue(Utc::now()));
+    }
 }
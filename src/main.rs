@@ -10,21 +10,41 @@ extern crate tokio;
 
 use std::process::exit;
 
-use clap::{arg, command};
+use chrono::NaiveDate;
+use clap::{arg, command, value_parser, ArgMatches, Command};
 use env_logger::Env;
 use log::{debug, error, info};
 
-use crate::kplc::KPLCBillQuery;
-use crate::settings::Settings;
+use crate::{
+    commands::export::{ExportOptions, Format, Records},
+    metrics::Metrics,
+    settings::Settings,
+};
 
+mod alert;
+mod anomaly;
+mod budget;
+mod calendar;
 mod channels;
+mod chart;
 mod client;
+mod commands;
+mod consumption;
+mod credit;
+mod dashboard;
+mod estimate;
 mod kplc;
+mod metrics;
+mod payment;
+mod server;
 mod settings;
+mod state;
+mod tariff;
+#[cfg(test)]
+mod test_utils;
 
-#[tokio::main]
-async fn main() {
-    let matches = command!()
+fn cli() -> Command {
+    command!()
         .arg(arg!(--"account-number" <NUMBER> "Account number to query").required(true))
         .arg(arg!(-c --config <FILE> "Config file to use").required(true))
         .arg(
@@ -39,7 +59,65 @@ async fn main() {
                 .value_parser(["auto", "always", "none"])
                 .default_value("auto"),
         )
-        .get_matches();
+        .subcommand(
+            Command::new("daemon").about("Check bills on an interval and serve metrics over HTTP"),
+        )
+        .subcommand(
+            Command::new("show").about("Show the balance and latest bill as of the last check"),
+        )
+        .subcommand(
+            Command::new("calendar").about("Print an iCalendar of the due dates of stored bills"),
+        )
+        .subcommand(Command::new("stats").about("Show consumption worked out from meter readings"))
+        .subcommand(
+            Command::new("export")
+                .about("Export the stored history of bills or meter readings")
+                .arg(
+                    arg!(--format <FORMAT> "Output format")
+                        .required(false)
+                        .value_parser(["csv", "jsonl"])
+                        .default_value("csv"),
+                )
+                .arg(
+                    arg!(--records <RECORDS> "Records to export")
+                        .required(false)
+                        .value_parser(["bills", "readings"])
+                        .default_value("bills"),
+                )
+                .arg(
+                    arg!(--from <DATE> "Export records from this date (YYYY-MM-DD)")
+                        .required(false)
+                        .value_parser(value_parser!(NaiveDate)),
+                )
+                .arg(
+                    arg!(--to <DATE> "Export records up to this date (YYYY-MM-DD)")
+                        .required(false)
+                        .value_parser(value_parser!(NaiveDate)),
+                ),
+        )
+}
+
+fn export_options(matches: &ArgMatches) -> ExportOptions {
+    let format = match matches.get_one::<String>("format").unwrap().as_str() {
+        "jsonl" => Format::Jsonl,
+        _ => Format::Csv,
+    };
+    let records = match matches.get_one::<String>("records").unwrap().as_str() {
+        "readings" => Records::Readings,
+        _ => Records::Bills,
+    };
+
+    ExportOptions {
+        format,
+        records,
+        from: matches.get_one::<NaiveDate>("from").copied(),
+        to: matches.get_one::<NaiveDate>("to").copied(),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let matches = cli().get_matches();
 
     let account_number = matches.get_one::<String>("account-number").unwrap();
     let config_path = matches.get_one::<String>("config").unwrap();
@@ -62,39 +140,53 @@ async fn main() {
         }
     };
 
-    let kplc_settings = settings.kplc.clone();
-    let kplc_query = KPLCBillQuery::new(kplc_settings);
-    info!("fetching bill from KPLC");
-    let bill = match kplc_query.get_bill(account_number).await {
-        Ok(bill) => {
-            info!("done fetching bill from KPLC");
-            bill
-        }
-        Err(err) => {
-            error!("error fetching bill from KPLC: {}", err);
-            exit(1);
+    let result = match matches.subcommand() {
+        Some(("show", _)) => commands::show::run(&settings, account_number),
+        Some(("calendar", _)) => commands::calendar::run(&settings, account_number),
+        Some(("stats", _)) => commands::stats::run(&settings, account_number),
+        Some(("export", export_matches)) => {
+            commands::export::run(&settings, account_number, &export_options(export_matches))
         }
+        Some(("daemon", _)) => commands::daemon::run(settings, account_number).await,
+        _ => commands::check::run(&settings, account_number, &Metrics::default()).await,
     };
 
-    if bill.data.balance.is_sign_negative() {
-        info!("balance present... sending alert to enabled channels");
-        for channel in channels::get_channels(&settings) {
-            let channel_name = channel.name();
-
-            if channel.is_enabled() {
-                info!("sending alert to {}", channel_name);
-                match channel.send_alert(&bill).await {
-                    Ok(_) => info!("sent alert to {}", channel_name),
-                    Err(err) => {
-                        error!("error sending alert to {}: {}", channel_name, err);
-                        exit(1);
-                    }
-                };
-            }
-        }
-    } else {
-        info!("no balance present");
+    if let Err(err) = result {
+        error!("{:#}", err);
+        exit(1);
     }
 
     info!("done!");
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{cli, export_options};
+    use crate::commands::export::{Format, Records};
+
+    #[test]
+    fn test_cli() {
+        cli().debug_assert();
+    }
+
+    #[test]
+    fn test_export_options() {
+        let matches = cli().get_matches_from([
+            "kplc-bill-alert",
+            "--account-number=123456",
+            "--config=config.toml",
+            "export",
+            "--format=jsonl",
+            "--from=2023-01-01",
+        ]);
+        let (_, export_matches) = matches.subcommand().unwrap();
+        let options = export_options(export_matches);
+
+        assert_eq!(options.format, Format::Jsonl);
+        assert_eq!(options.records, Records::Bills);
+        assert_eq!(options.from, NaiveDate::from_ymd_opt(2023, 1, 1));
+        assert_eq!(options.to, None);
+    }
+}
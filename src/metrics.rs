@@ -0,0 +1,239 @@
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex};
+
+use chrono::prelude::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::kplc::KPLCBill;
+
+/// Counters and gauges kept while running as a daemon, exposed in the
+/// Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    accounts: BTreeMap<String, AccountMetrics>,
+    // (channel, outcome) -> count
+    alerts: BTreeMap<(String, &'static str), u64>,
+}
+
+#[derive(Default)]
+struct AccountMetrics {
+    balance: Option<Decimal>,
+    due_date: Option<DateTime<Utc>>,
+    last_bill_amount: Option<Decimal>,
+    // meter serial number -> reading
+    meter_readings: BTreeMap<String, usize>,
+    last_fetch: Option<DateTime<Utc>>,
+    fetch_failures: u64,
+}
+
+impl Metrics {
+    /// Updates the account's gauges from a successfully fetched bill.
+    pub fn record_fetch(&self, account_number: &str, bill: &KPLCBill, now: DateTime<Utc>) {
+        let mut inner = self.inner.lock().unwrap();
+        let account = inner
+            .accounts
+            .entry(account_number.to_string())
+            .or_default();
+
+        account.balance = Some(bill.data.balance);
+        // the oldest unpaid bill is the first to fall due
+        account.due_date = bill
+            .unpaid_bills()
+            .last()
+            .map(|unpaid_bill| unpaid_bill.due_date);
+        account.last_bill_amount = bill
+            .data
+            .col_bills
+            .first()
+            .map(|latest_bill| latest_bill.bill_amount);
+        for meter in bill.data.meter_list.iter() {
+            if let Some(usage) = meter
+                .latest_usage_list
+                .iter()
+                .max_by_key(|u| u.reading_date)
+            {
+                account
+                    .meter_readings
+                    .insert(meter.serial_num.clone(), usage.reading_value);
+            }
+        }
+        account.last_fetch = Some(now);
+    }
+
+    pub fn record_fetch_failure(&self, account_number: &str) {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner
+            .accounts
+            .entry(account_number.to_string())
+            .or_default()
+            .fetch_failures += 1;
+    }
+
+    pub fn record_alert(&self, channel: &str, sent: bool) {
+        let outcome = if sent { "sent" } else { "failed" };
+        let mut inner = self.inner.lock().unwrap();
+
+        *inner
+            .alerts
+            .entry((channel.to_string(), outcome))
+            .or_default() += 1;
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self, now: DateTime<Utc>) -> String {
+        let inner = self.inner.lock().unwrap();
+        let mut out = String::new();
+
+        let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            writeln!(out, "# HELP {name} {help}").unwrap();
+            writeln!(out, "# TYPE {name} {kind}").unwrap();
+            for (labels, value) in samples {
+                writeln!(out, "{name}{{{labels}}} {value}").unwrap();
+            }
+        };
+
+        let accounts = || inner.accounts.iter();
+        let account_label = |account_number: &str| format!("account=\"{account_number}\"");
+
+        family(
+            "kplc_balance_kes",
+            "gauge",
+            "Account balance, negative when owed.",
+            accounts()
+                .filter_map(|(n, a)| Some((account_label(n), a.balance?.to_string())))
+                .collect(),
+        );
+        family(
+            "kplc_days_until_due",
+            "gauge",
+            "Days until the oldest unpaid bill is due, negative once overdue.",
+            accounts()
+                .filter_map(|(n, a)| {
+                    let days = (a.due_date? - now).num_seconds() as f64 / 86400.0;
+                    Some((account_label(n), format!("{days:.2}")))
+                })
+                .collect(),
+        );
+        family(
+            "kplc_last_bill_amount_kes",
+            "gauge",
+            "Amount of the latest bill.",
+            accounts()
+                .filter_map(|(n, a)| Some((account_label(n), a.last_bill_amount?.to_string())))
+                .collect(),
+        );
+        family(
+            "kplc_meter_reading_kwh",
+            "gauge",
+            "Latest meter reading.",
+            accounts()
+                .flat_map(|(n, a)| {
+                    a.meter_readings.iter().map(move |(serial_num, reading)| {
+                        (
+                            format!("{},meter=\"{serial_num}\"", account_label(n)),
+                            reading.to_string(),
+                        )
+                    })
+                })
+                .collect(),
+        );
+        family(
+            "kplc_last_fetch_timestamp_seconds",
+            "gauge",
+            "Unix time of the last successful bill fetch.",
+            accounts()
+                .filter_map(|(n, a)| {
+                    Some((account_label(n), a.last_fetch?.timestamp().to_string()))
+                })
+                .collect(),
+        );
+        family(
+            "kplc_fetch_failures_total",
+            "counter",
+            "Failed attempts at fetching the bill from KPLC.",
+            accounts()
+                .map(|(n, a)| (account_label(n), a.fetch_failures.to_string()))
+                .collect(),
+        );
+        family(
+            "kplc_alerts_total",
+            "counter",
+            "Alerts sent, by channel and outcome.",
+            inner
+                .alerts
+                .iter()
+                .map(|((channel, outcome), count)| {
+                    (
+                        format!("channel=\"{channel}\",outcome=\"{outcome}\""),
+                        count.to_string(),
+                    )
+                })
+                .collect(),
+        );
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::Metrics;
+    use crate::test_utils::get_kplc_bill_resp;
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::default();
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let now = Utc.with_ymd_and_hms(2022, 10, 20, 21, 0, 0).unwrap();
+
+        metrics.record_fetch("1234567", &bill, now);
+        metrics.record_fetch_failure("1234567");
+        metrics.record_alert("pushover", true);
+        metrics.record_alert("pushover", true);
+        metrics.record_alert("pushover", false);
+
+        assert_eq!(
+            metrics.render(now),
+            "# HELP kplc_balance_kes Account balance, negative when owed.\n\
+             # TYPE kplc_balance_kes gauge\n\
+             kplc_balance_kes{account=\"1234567\"} -3592.34\n\
+             # HELP kplc_days_until_due Days until the oldest unpaid bill is due, negative once overdue.\n\
+             # TYPE kplc_days_until_due gauge\n\
+             kplc_days_until_due{account=\"1234567\"} 5.00\n\
+             # HELP kplc_last_bill_amount_kes Amount of the latest bill.\n\
+             # TYPE kplc_last_bill_amount_kes gauge\n\
+             kplc_last_bill_amount_kes{account=\"1234567\"} 3593\n\
+             # HELP kplc_meter_reading_kwh Latest meter reading.\n\
+             # TYPE kplc_meter_reading_kwh gauge\n\
+             kplc_meter_reading_kwh{account=\"1234567\",meter=\"981928391283\"} 18234\n\
+             # HELP kplc_last_fetch_timestamp_seconds Unix time of the last successful bill fetch.\n\
+             # TYPE kplc_last_fetch_timestamp_seconds gauge\n\
+             kplc_last_fetch_timestamp_seconds{account=\"1234567\"} 1666299600\n\
+             # HELP kplc_fetch_failures_total Failed attempts at fetching the bill from KPLC.\n\
+             # TYPE kplc_fetch_failures_total counter\n\
+             kplc_fetch_failures_total{account=\"1234567\"} 1\n\
+             # HELP kplc_alerts_total Alerts sent, by channel and outcome.\n\
+             # TYPE kplc_alerts_total counter\n\
+             kplc_alerts_total{channel=\"pushover\",outcome=\"failed\"} 1\n\
+             kplc_alerts_total{channel=\"pushover\",outcome=\"sent\"} 2\n"
+        );
+    }
+
+    #[test]
+    fn test_render_without_metrics() {
+        let metrics = Metrics::default();
+
+        assert!(metrics
+            .render(Utc::now())
+            .lines()
+            .all(|line| line.starts_with('#')));
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::prelude::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::{kplc::KPLCBill, state::AccountState};
+
+/// A payment found to have reached KPLC since the previous run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payment {
+    pub amount: Decimal,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Compares a freshly fetched bill with the account's history from the
+/// previous run, before the bill is recorded.
+///
+/// A payment is detected when the balance goes from owed to non-negative, or
+/// else when bills that had an amount pending are now cleared.
+pub fn detect(
+    account_state: &AccountState,
+    bill: &KPLCBill,
+    now: DateTime<Utc>,
+) -> Option<Payment> {
+    let amount = match account_state.balance {
+        Some(balance) if balance.is_sign_negative() && !bill.data.balance.is_sign_negative() => {
+            balance.abs()
+        }
+        _ => bill
+            .data
+            .col_bills
+            .iter()
+            .filter(|col_bill| col_bill.bill_pend_amount.is_zero())
+            .filter_map(|col_bill| {
+                account_state
+                    .bills
+                    .iter()
+                    .find(|record| record.bill_number == col_bill.bill_number)
+            })
+            .map(|record| record.bill_pend_amount)
+            .filter(|pend_amount| pend_amount.is_sign_positive())
+            .sum(),
+    };
+
+    if amount.is_zero() {
+        return None;
+    }
+
+    Some(Payment {
+        amount,
+        detected_at: now,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::{detect, Payment};
+    use crate::{state::AccountState, test_utils::get_kplc_bill_resp};
+
+    #[test]
+    fn test_detect_nothing_paid() {
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let mut account_state = AccountState::default();
+        let now = Utc::now();
+
+        // nothing to compare with on the first run
+        assert_eq!(detect(&account_state, &bill, now), None);
+
+        account_state.record(&bill);
+        assert_eq!(detect(&account_state, &bill, now), None);
+    }
+
+    #[test]
+    fn test_detect_balance_cleared() {
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let mut account_state = AccountState::default();
+        account_state.record(&bill);
+
+        bill.data.balance = Decimal::new(40766, 2);
+        bill.data.col_bills[0].bill_pend_amount = Decimal::ZERO;
+        let now = Utc.with_ymd_and_hms(2022, 10, 20, 6, 0, 0).unwrap();
+
+        assert_eq!(
+            detect(&account_state, &bill, now),
+            Some(Payment {
+                amount: Decimal::new(359234, 2),
+                detected_at: now,
+            })
+        );
+    }
+
+    #[test]
+    fn test_detect_pending_cleared() {
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let mut account_state = AccountState::default();
+        account_state.record(&bill);
+
+        // part payment, leaving the balance owed
+        bill.data.balance = Decimal::new(-1000, 0);
+        bill.data.col_bills[0].bill_pend_amount = Decimal::ZERO;
+        let now = Utc::now();
+
+        assert_eq!(
+            detect(&account_state, &bill, now).map(|payment| payment.amount),
+            Some(Decimal::new(359234, 2))
+        );
+    }
+}
@@ -0,0 +1,490 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use log::{error, info, warn};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use crate::{
+    calendar,
+    commands::check,
+    consumption::ConsumptionPeriod,
+    dashboard,
+    metrics::Metrics,
+    settings::Settings,
+    state::{self, AccountState, BillRecord, MeterReading},
+};
+
+/// Shared with every request handler.
+#[derive(Clone)]
+pub struct AppState {
+    pub settings: Arc<Settings>,
+    pub metrics: Arc<Metrics>,
+    /// Accounts the daemon checks, and the only ones served.
+    pub account_numbers: Arc<Vec<String>>,
+    /// Held while checking an account, so that only one check at a time
+    /// writes the state file.
+    pub check_lock: Arc<Mutex<()>>,
+    /// When each account was last refreshed through the API.
+    pub refreshed_at: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+// refreshing sends alerts, so it's allowed at most this often per account
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+pub fn router(state: AppState) -> Router {
+    let mut router = Router::new().route("/metrics", get(metrics));
+
+    if state.settings.daemon.api {
+        router = router
+            .route("/accounts", get(accounts))
+            .route("/accounts/:account_number/bill", get(bill))
+            .route("/accounts/:account_number/history", get(history))
+            .route("/accounts/:account_number/refresh", post(refresh))
+            .route("/accounts/:account_number/calendar.ics", get(calendar));
+    }
+
+    if state.settings.daemon.dashboard {
+        router = router.route("/", get(dashboard));
+    }
+
+    router.with_state(state)
+}
+
+/// Serves the daemon's HTTP endpoints until an error occurs.
+pub async fn serve(listen: &str, state: AppState) -> Result<()> {
+    let addr: SocketAddr = listen
+        .parse()
+        .with_context(|| format!("invalid listen address {listen}"))?;
+
+    info!("listening on http://{}", addr);
+    axum::Server::try_bind(&addr)
+        .with_context(|| format!("failed to listen on {addr}"))?
+        .serve(router(state).into_make_service())
+        .await
+        .context("error serving HTTP")
+}
+
+/// An error returned to API clients as JSON.
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+#[derive(Serialize)]
+struct AccountSummary {
+    account_number: String,
+    balance: Option<Decimal>,
+    latest_bill: Option<BillRecord>,
+}
+
+#[derive(Serialize)]
+struct AccountHistory {
+    account_number: String,
+    bills: Vec<BillRecord>,
+    readings: Vec<MeterReading>,
+    consumption: Vec<ConsumptionPeriod>,
+}
+
+impl AppState {
+    /// Loads the stored state of an account the daemon checks.
+    fn account_state(&self, account_number: &str) -> Result<AccountState, ApiError> {
+        if !self.account_numbers.iter().any(|n| n == account_number) {
+            return Err(ApiError(
+                StatusCode::NOT_FOUND,
+                format!("unknown account {account_number}"),
+            ));
+        }
+
+        let mut state = state::State::load(&self.settings.state.path).map_err(|err| {
+            error!("error loading state: {:#}", err);
+            ApiError(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "error loading state".to_string(),
+            )
+        })?;
+
+        Ok(state.accounts.remove(account_number).unwrap_or_default())
+    }
+
+    fn account_summary(&self, account_number: &str) -> Result<AccountSummary, ApiError> {
+        let account_state = self.account_state(account_number)?;
+
+        Ok(AccountSummary {
+            account_number: account_number.to_string(),
+            balance: account_state.balance,
+            latest_bill: account_state.bills.into_iter().next(),
+        })
+    }
+}
+
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(Utc::now()),
+    )
+}
+
+async fn dashboard(State(state): State<AppState>) -> Result<Html<String>, ApiError> {
+    let accounts = state
+        .account_numbers
+        .iter()
+        .map(|account_number| Ok((account_number.clone(), state.account_state(account_number)?)))
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    Ok(Html(dashboard::render(&accounts, Utc::now())))
+}
+
+async fn accounts(State(state): State<AppState>) -> Result<Json<Vec<AccountSummary>>, ApiError> {
+    state
+        .account_numbers
+        .iter()
+        .map(|account_number| state.account_summary(account_number))
+        .collect::<Result<_, _>>()
+        .map(Json)
+}
+
+async fn bill(
+    State(state): State<AppState>,
+    Path(account_number): Path<String>,
+) -> Result<Json<AccountSummary>, ApiError> {
+    state.account_summary(&account_number).map(Json)
+}
+
+async fn history(
+    State(state): State<AppState>,
+    Path(account_number): Path<String>,
+) -> Result<Json<AccountHistory>, ApiError> {
+    let account_state = state.account_state(&account_number)?;
+
+    Ok(Json(AccountHistory {
+        account_number,
+        bills: account_state.bills,
+        readings: account_state.readings,
+        consumption: account_state.consumption,
+    }))
+}
+
+async fn calendar(
+    State(state): State<AppState>,
+    Path(account_number): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let account_state = state.account_state(&account_number)?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        calendar::render(
+            &account_number,
+            &account_state.bills,
+            &state.settings.calendar,
+            Utc::now(),
+        ),
+    ))
+}
+
+/// Checks the account's bill right away, as the daemon does on its interval.
+/// Failing to send alerts doesn't fail the request, as the bill is still fetched.
+async fn refresh(
+    State(state): State<AppState>,
+    Path(account_number): Path<String>,
+) -> Result<Json<AccountSummary>, ApiError> {
+    // fail early on unknown accounts, before reaching out to KPLC
+    state.account_state(&account_number)?;
+
+    {
+        let mut refreshed_at = state.refreshed_at.lock().await;
+        let now = Instant::now();
+        if let Some(last) = refreshed_at.get(&account_number) {
+            if now.duration_since(*last) < REFRESH_INTERVAL {
+                return Err(ApiError(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    format!(
+                        "account {account_number} was refreshed less than {} minutes ago",
+                        REFRESH_INTERVAL.as_secs() / 60
+                    ),
+                ));
+            }
+        }
+        refreshed_at.insert(account_number.clone(), now);
+    }
+
+    {
+        let _guard = state.check_lock.lock().await;
+        info!("refreshing account {}", account_number);
+        match check::run(&state.settings, &account_number, &state.metrics).await {
+            Ok(()) => {}
+            // the bill was still fetched and recorded
+            Err(err) if err.is::<check::AlertsFailed>() => {
+                warn!("error alerting on account {}: {:#}", account_number, err);
+            }
+            Err(err) => {
+                error!("error checking account {}: {:#}", account_number, err);
+                // nothing was refreshed, so don't hold off the next attempt
+                state.refreshed_at.lock().await.remove(&account_number);
+                return Err(ApiError(StatusCode::BAD_GATEWAY, format!("{err:#}")));
+            }
+        }
+    }
+
+    state.account_summary(&account_number).map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs, sync::Arc, time::Instant};
+
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+        Router,
+    };
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+    use tempfile::{tempdir, TempDir};
+    use tokio::sync::Mutex;
+    use tower::ServiceExt;
+
+    use super::{router, AppState};
+    use crate::{metrics::Metrics, settings::Settings};
+
+    fn make_state(enabled: bool) -> (AppState, TempDir) {
+        let tmp_dir = tempdir().unwrap();
+        let state_path = tmp_dir.path().join("state.json");
+        let config_path = tmp_dir.path().join("config.toml");
+
+        fs::write(
+            &config_path,
+            format!(
+                r###"
+[kplc]
+basic_auth = "Basic asdasldkasdlasd"
+token_url = "https://selfservice.kplc.co.ke/api/token"
+bill_url = "https://selfservice.kplc.co.ke/api/publicData/2.0.1/"
+token_grant_type = "client_credentials"
+token_scope = "token_public"
+
+[state]
+path = "{}"
+
+[daemon]
+api = {enabled}
+dashboard = {enabled}
+"###,
+                state_path.display()
+            ),
+        )
+        .unwrap();
+        fs::write(
+            &state_path,
+            r###"{"accounts": {"1234567": {
+                "balance": -3592.34,
+                "bills": [{
+                    "bill_number": "981239123213",
+                    "billing_period": "10 - October 2022",
+                    "from_date": "2022-09-07T21:00:00Z",
+                    "to_date": "2022-10-04T21:00:00Z",
+                    "due_date": "2022-10-25T21:00:00Z",
+                    "bill_amount": "3593",
+                    "bill_pend_amount": "3592.34"
+                }],
+                "readings": [{
+                    "serial_num": "981928391283",
+                    "reading_date": "2022-10-04T21:00:00Z",
+                    "reading_value": 18234
+                }]
+            }}}"###,
+        )
+        .unwrap();
+
+        let settings = Settings::new(config_path.to_str().unwrap()).unwrap();
+        let state = AppState {
+            settings: Arc::new(settings),
+            metrics: Arc::new(Metrics::default()),
+            account_numbers: Arc::new(vec!["1234567".to_string()]),
+            check_lock: Arc::new(Mutex::new(())),
+            refreshed_at: Arc::new(Mutex::new(HashMap::new())),
+        };
+
+        (state, tmp_dir)
+    }
+
+    fn make_app(enabled: bool) -> (Router, TempDir) {
+        let (state, tmp_dir) = make_state(enabled);
+
+        (router(state), tmp_dir)
+    }
+
+    async fn request(app: Router, request: Request<Body>) -> (StatusCode, String) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    async fn get(app: Router, uri: &str) -> (StatusCode, String) {
+        request(app, Request::get(uri).body(Body::empty()).unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let (app, _tmp_dir) = make_app(false);
+
+        let (status, body) = get(app, "/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with("# HELP kplc_balance_kes"));
+    }
+
+    #[tokio::test]
+    async fn test_api_disabled() {
+        let (app, _tmp_dir) = make_app(false);
+
+        let (status, _) = get(app.clone(), "/accounts").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get(app, "/").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_dashboard() {
+        let (app, _tmp_dir) = make_app(true);
+
+        let (status, body) = get(app, "/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<h2>Account 1234567</h2>"));
+        assert!(body.contains("Balance: <b>KES 3592.34 in arrears</b>"));
+    }
+
+    #[tokio::test]
+    async fn test_accounts() {
+        let (app, _tmp_dir) = make_app(true);
+
+        let (status, body) = get(app, "/accounts").await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            body,
+            json!([{
+                "account_number": "1234567",
+                "balance": "-3592.34",
+                "latest_bill": {
+                    "bill_number": "981239123213",
+                    "billing_period": "10 - October 2022",
+                    "from_date": "2022-09-07T21:00:00Z",
+                    "to_date": "2022-10-04T21:00:00Z",
+                    "due_date": "2022-10-25T21:00:00Z",
+                    "bill_amount": "3593",
+                    "bill_pend_amount": "3592.34"
+                }
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bill() {
+        let (app, _tmp_dir) = make_app(true);
+
+        let (status, body) = get(app.clone(), "/accounts/1234567/bill").await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["latest_bill"]["bill_number"], "981239123213");
+
+        let (status, body) = get(app, "/accounts/7654321/bill").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, "{\"error\":\"unknown account 7654321\"}");
+    }
+
+    #[tokio::test]
+    async fn test_history() {
+        let (app, _tmp_dir) = make_app(true);
+
+        let (status, body) = get(app, "/accounts/1234567/history").await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["bills"].as_array().unwrap().len(), 1);
+        assert_eq!(body["readings"][0]["reading_value"], 18234);
+        assert_eq!(body["consumption"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_calendar() {
+        let (app, _tmp_dir) = make_app(true);
+
+        let (status, body) = get(app, "/accounts/1234567/calendar.ics").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(body.contains("DTSTART;VALUE=DATE:20221025\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_unknown_account() {
+        let (app, _tmp_dir) = make_app(true);
+
+        let (status, _) = request(
+            app,
+            Request::post("/accounts/7654321/refresh")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_too_often() {
+        let (state, _tmp_dir) = make_state(true);
+        state
+            .refreshed_at
+            .lock()
+            .await
+            .insert("1234567".to_string(), Instant::now());
+
+        let (status, body) = request(
+            router(state),
+            Request::post("/accounts/1234567/refresh")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            json!({"error": "account 1234567 was refreshed less than 5 minutes ago"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refresh_failed_fetch() {
+        let (mut state, _tmp_dir) = make_state(true);
+        Arc::get_mut(&mut state.settings).unwrap().kplc.token_url =
+            "http://127.0.0.1:1/api/token".to_string();
+
+        let (status, _) = request(
+            router(state.clone()),
+            Request::post("/accounts/1234567/refresh")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(state.refreshed_at.lock().await.is_empty());
+    }
+}
@@ -1,14 +1,103 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
 use config::{Config, ConfigError};
+use rust_decimal::Decimal;
 use serde::Deserialize;
 
-use crate::{channels::pushover::PushoverSettings, kplc::KPLCSettings};
+use crate::{
+    anomaly::AnomalySettings,
+    calendar::CalendarSettings,
+    channels::{
+        apprise::AppriseSettings, email::EmailSettings, exec::ExecSettings, file::FileSettings,
+        google_chat::GoogleChatSettings, gotify::GotifySettings, matrix::MatrixSettings,
+        mqtt::MqttSettings, ntfy::NtfySettings, pushover::PushoverSettings, signal::SignalSettings,
+        stdout::StdoutSettings, teams::TeamsSettings, telegram::TelegramSettings,
+        whatsapp::WhatsAppSettings,
+    },
+    commands::daemon::DaemonSettings,
+    credit::CreditSettings,
+    estimate::EstimateSettings,
+    kplc::KPLCSettings,
+    state::StateSettings,
+    tariff::TariffSettings,
+};
 
 #[derive(Deserialize, Debug)]
 pub struct Settings {
     pub kplc: KPLCSettings,
 
+    #[serde(default)]
+    pub state: StateSettings,
+
+    #[serde(default)]
+    pub daemon: DaemonSettings,
+
+    pub anomaly: Option<AnomalySettings>,
+
+    pub estimated_reading: Option<EstimateSettings>,
+
+    pub tariff: Option<TariffSettings>,
+
+    pub credit: Option<CreditSettings>,
+
+    #[serde(default)]
+    pub calendar: CalendarSettings,
+
+    /// Settings for individual accounts, keyed by account number.
+    #[serde(default)]
+    pub accounts: HashMap<String, AccountSettings>,
+
+    #[serde(default)]
     pub pushover: PushoverSettings,
+
+    #[serde(default)]
+    pub mqtt: MqttSettings,
+
+    #[serde(default)]
+    pub ntfy: NtfySettings,
+
+    #[serde(default)]
+    pub gotify: GotifySettings,
+
+    #[serde(default)]
+    pub matrix: MatrixSettings,
+
+    #[serde(default)]
+    pub teams: TeamsSettings,
+
+    #[serde(default)]
+    pub google_chat: GoogleChatSettings,
+
+    #[serde(default)]
+    pub whatsapp: WhatsAppSettings,
+
+    #[serde(default)]
+    pub signal: SignalSettings,
+
+    #[serde(default)]
+    pub telegram: TelegramSettings,
+
+    #[serde(default)]
+    pub email: EmailSettings,
+
+    #[serde(default)]
+    pub exec: ExecSettings,
+
+    #[serde(default)]
+    pub stdout: StdoutSettings,
+
+    #[serde(default)]
+    pub file: FileSettings,
+
+    #[serde(default)]
+    pub apprise: AppriseSettings,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct AccountSettings {
+    /// Most the account should be billed per billing cycle, in KES.
+    pub budget: Option<Decimal>,
 }
 
 impl Settings {
@@ -19,13 +108,26 @@ impl Settings {
 
         s.try_deserialize()
     }
+
+    pub fn account(&self, account_number: &str) -> AccountSettings {
+        self.accounts
+            .get(account_number)
+            .cloned()
+            .unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::io::Write;
 
+    use rust_decimal::Decimal;
+
     use super::Settings;
+    use crate::{
+        alert::Severity,
+        channels::{email::SmtpSecurity, whatsapp::TemplateParameter},
+    };
 
     use pretty_assertions::assert_eq;
     use std::fs::File;
@@ -45,11 +147,125 @@ bill_url = "https://selfservice.kplc.co.ke/api/publicData/2.0.1/"
 token_grant_type = "client_credentials"
 token_scope = "token_public"
 
+[state]
+path = "/var/lib/kplc-bill-alert/state.json"
+
+[daemon]
+listen = "0.0.0.0:9184"
+interval_minutes = 60
+api = true
+dashboard = true
+
+[anomaly]
+percent_above_average = 50
+
+[estimated_reading]
+unchanged_readings = 2
+
+[tariff]
+tolerance_percent = 10
+
+[credit]
+cover_percent = 80
+
+[calendar]
+reminder_days = [7, 2]
+
+[accounts.1234567]
+budget = 4800
+
 [pushover]
 enabled = true
 token = "asdasdasdqe123"
 user_key = "asd13414nkj1k2j412"
 api_url = "https://api.pushover.net/1/messages.json"
+url = "https://selfservice.kplc.co.ke"
+
+[pushover.severity.critical]
+priority = 2
+sound = "siren"
+
+[mqtt]
+enabled = true
+host = "mosquitto.local"
+username = "kplc"
+password = "secret"
+
+[ntfy]
+enabled = true
+server_url = "https://ntfy.example.com"
+topic = "kplc"
+tags = ["zap"]
+
+[gotify]
+enabled = true
+server_url = "https://gotify.example.com"
+app_token = "AzXa1Ds"
+markdown = true
+
+[gotify.priority]
+critical = 9
+
+[matrix]
+enabled = true
+homeserver_url = "https://matrix.example.org"
+access_token = "syt_asdasd"
+room_id = "!bills:example.org"
+
+[teams]
+enabled = true
+webhook_url = "https://example.webhook.office.com/webhookb2/asdasd"
+
+[google_chat]
+enabled = false
+webhook_url = "https://chat.googleapis.com/v1/spaces/AAAA/messages?key=asdasd&token=qwe123"
+
+[whatsapp]
+enabled = true
+phone_number_id = "106540352242922"
+access_token = "EAAJB"
+recipients = ["254712345678"]
+template = "kplc_bill_due"
+parameters = ["account", "balance", "due_date", "bill_amount"]
+
+[signal]
+enabled = true
+api_url = "https://signal-api.local"
+number = "+254700000000"
+recipients = ["+254712345678"]
+group_ids = ["group.ZmFtaWx5"]
+
+[telegram]
+enabled = true
+bot_token = "123456789:abcdefg"
+chat_ids = ["-100123456"]
+
+[email]
+enabled = true
+smtp_host = "smtp.example.com"
+username = "alerts@example.com"
+password = "asdasdasd"
+from = "KPLC Alerts <alerts@example.com>"
+to = ["jane@example.com"]
+
+[exec]
+enabled = true
+command = ["/usr/local/bin/notify-bill", "--quiet"]
+
+[stdout]
+enabled = true
+
+[file]
+enabled = true
+path = "/var/log/kplc/alerts.log"
+template = "{sent_at} {title}"
+max_files = 3
+
+[apprise]
+urls = [
+    "pover://uQiRzpo4DXghDmr9QzzfQu27cmVRsG@azGDORePK8gMaC0QOYAMyEEuzJnyUi",
+    "ntfys://ntfy.sh/kplc-bills",
+]
 "###;
         config_file.write_all(conf.as_bytes()).unwrap();
 
@@ -69,9 +285,151 @@ api_url = "https://api.pushover.net/1/messages.json"
         assert_eq!(settings.kplc.token_grant_type, "client_credentials");
         assert_eq!(settings.kplc.token_scope, "token_public");
 
+        assert_eq!(settings.state.path, "/var/lib/kplc-bill-alert/state.json");
+        assert_eq!(settings.daemon.listen, "0.0.0.0:9184");
+        assert_eq!(settings.daemon.interval_minutes, 60);
+        assert!(settings.daemon.api);
+        assert!(settings.daemon.dashboard);
+
+        assert_eq!(
+            settings.account("1234567").budget,
+            Some(Decimal::new(4800, 0))
+        );
+        assert_eq!(settings.account("7654321").budget, None);
+
+        let anomaly = settings.anomaly.unwrap();
+        assert_eq!(anomaly.window, 6);
+        assert_eq!(anomaly.percent_above_average, Some(50.0));
+        assert_eq!(anomaly.z_score, None);
+
+        assert_eq!(settings.estimated_reading.unwrap().unchanged_readings, 2);
+
+        let tariff = settings.tariff.unwrap();
+        assert_eq!(tariff.path, None);
+        assert_eq!(tariff.tolerance_percent, 10.0);
+
+        assert_eq!(settings.credit.unwrap().cover_percent, Decimal::new(80, 0));
+
+        assert_eq!(settings.calendar.reminder_days, vec![7, 2]);
+
         assert_eq!(settings.pushover.enabled, true);
         assert_eq!(settings.pushover.token, "asdasdasdqe123");
         assert_eq!(settings.pushover.user_key, "asd13414nkj1k2j412");
+        assert_eq!(
+            settings.pushover.url.as_deref(),
+            Some("https://selfservice.kplc.co.ke")
+        );
+        let critical = &settings.pushover.severity[&Severity::Critical];
+        assert_eq!(critical.priority, Some(2));
+        assert_eq!(critical.sound.as_deref(), Some("siren"));
+
+        assert!(settings.mqtt.enabled);
+        assert_eq!(settings.mqtt.host, "mosquitto.local");
+        assert_eq!(settings.mqtt.port, 1883);
+        assert_eq!(settings.mqtt.username.as_deref(), Some("kplc"));
+        assert_eq!(settings.mqtt.topic_prefix, "kplc");
+
+        assert!(settings.ntfy.enabled);
+        assert_eq!(settings.ntfy.server_url, "https://ntfy.example.com");
+        assert_eq!(settings.ntfy.topic, "kplc");
+        assert_eq!(settings.ntfy.tags, vec!["zap"]);
+        assert!(!settings.ntfy.markdown);
+
+        assert!(settings.gotify.enabled);
+        assert_eq!(settings.gotify.app_token, "AzXa1Ds");
+        assert!(settings.gotify.markdown);
+        assert_eq!(settings.gotify.priority[&Severity::Critical], 9);
+
+        assert!(settings.matrix.enabled);
+        assert_eq!(settings.matrix.homeserver_url, "https://matrix.example.org");
+        assert_eq!(settings.matrix.room_id, "!bills:example.org");
+
+        assert!(settings.teams.enabled);
+        assert_eq!(
+            settings.teams.webhook_url,
+            "https://example.webhook.office.com/webhookb2/asdasd"
+        );
+        assert!(!settings.google_chat.enabled);
+        assert_eq!(
+            settings.google_chat.webhook_url,
+            "https://chat.googleapis.com/v1/spaces/AAAA/messages?key=asdasd&token=qwe123"
+        );
+
+        assert!(settings.whatsapp.enabled);
+        assert_eq!(settings.whatsapp.graph_url, "https://graph.facebook.com");
+        assert_eq!(settings.whatsapp.recipients, vec!["254712345678"]);
+        assert_eq!(settings.whatsapp.language, "en");
+        assert_eq!(
+            settings.whatsapp.parameters,
+            vec![
+                TemplateParameter::Account,
+                TemplateParameter::Balance,
+                TemplateParameter::DueDate,
+                TemplateParameter::BillAmount,
+            ]
+        );
+
+        assert!(settings.signal.enabled);
+        assert_eq!(settings.signal.number, "+254700000000");
+        assert_eq!(settings.signal.recipients, vec!["+254712345678"]);
+        assert_eq!(settings.signal.group_ids, vec!["group.ZmFtaWx5"]);
+        assert!(!settings.signal.styled);
+
+        assert!(settings.telegram.enabled);
+        assert_eq!(settings.telegram.api_url, "https://api.telegram.org");
+        assert_eq!(settings.telegram.bot_token, "123456789:abcdefg");
+        assert_eq!(settings.telegram.chat_ids, vec!["-100123456"]);
+
+        assert!(settings.email.enabled);
+        assert_eq!(settings.email.smtp_host, "smtp.example.com");
+        assert_eq!(settings.email.smtp_port, None);
+        assert_eq!(settings.email.security, SmtpSecurity::Starttls);
+        assert_eq!(settings.email.from, "KPLC Alerts <alerts@example.com>");
+        assert_eq!(settings.email.to, vec!["jane@example.com"]);
+
+        assert!(settings.exec.enabled);
+        assert_eq!(
+            settings.exec.command,
+            vec!["/usr/local/bin/notify-bill", "--quiet"]
+        );
+        assert_eq!(settings.exec.timeout_seconds, 30);
+
+        assert!(settings.stdout.enabled);
+        assert_eq!(settings.stdout.template, None);
+
+        assert!(settings.file.enabled);
+        assert_eq!(
+            settings.file.path,
+            std::path::PathBuf::from("/var/log/kplc/alerts.log")
+        );
+        assert_eq!(settings.file.template.as_deref(), Some("{sent_at} {title}"));
+        assert_eq!(settings.file.max_bytes, 10 * 1024 * 1024);
+        assert_eq!(settings.file.max_files, 3);
+
+        assert_eq!(settings.apprise.urls.len(), 2);
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_settings_from_file_without_channels() {
+        let tmp_dir = tempdir().unwrap();
+        let file_path = tmp_dir.path().join("config.toml");
+
+        let mut config_file = File::create(&file_path).unwrap();
+        let conf = r###"
+[kplc]
+basic_auth = "Basic asdasldkasdlasd"
+token_url = "https://selfservice.kplc.co.ke/api/token"
+bill_url = "https://selfservice.kplc.co.ke/api/publicData/2.0.1/"
+token_grant_type = "client_credentials"
+token_scope = "token_public"
+"###;
+        config_file.write_all(conf.as_bytes()).unwrap();
+
+        let settings = Settings::new(file_path.as_path().to_str().unwrap()).unwrap();
+        assert!(!settings.pushover.enabled);
+        assert!(settings.apprise.urls.is_empty());
 
         tmp_dir.close().unwrap();
     }
@@ -106,4 +464,125 @@ api_url = "https://api.pushover.net/1/messages.json"
 
         tmp_dir.close().unwrap();
     }
+
+    #[test]
+    fn test_settings_from_file_missing_channel_field() {
+        let tmp_dir = tempdir().unwrap();
+        let file_path = tmp_dir.path().join("config.toml");
+
+        // each channel section, less the required field
+        let channels = [
+            ("[ntfy]\nenabled = true", "topic"),
+            (
+                "[gotify]\nenabled = true\nserver_url = \"http://gotify.lan\"",
+                "app_token",
+            ),
+            (
+                "[matrix]\nenabled = true\nhomeserver_url = \"https://matrix.org\"\n\
+                 access_token = \"syt_asdasd\"",
+                "room_id",
+            ),
+            ("[teams]\nenabled = true", "webhook_url"),
+            ("[google_chat]\nenabled = true", "webhook_url"),
+            (
+                "[whatsapp]\nenabled = true\nphone_number_id = \"106540352242922\"\n\
+                 access_token = \"EAAJB\"\nrecipients = [\"254712345678\"]",
+                "template",
+            ),
+            (
+                "[signal]\nenabled = true\napi_url = \"http://localhost:8080\"",
+                "number",
+            ),
+            (
+                "[telegram]\nenabled = true\nbot_token = \"123456789:abcdefg\"",
+                "chat_ids",
+            ),
+            (
+                "[email]\nenabled = true\nsmtp_host = \"smtp.example.com\"\n\
+                 to = [\"jane@example.com\"]",
+                "from",
+            ),
+        ];
+
+        for (channel, field) in channels {
+            let mut config_file = File::create(&file_path).unwrap();
+            let conf = format!(
+                r###"
+[kplc]
+basic_auth = "Basic asdasldkasdlasd"
+token_url = "https://selfservice.kplc.co.ke/api/token"
+bill_url = "https://selfservice.kplc.co.ke/api/publicData/2.0.1/"
+token_grant_type = "client_credentials"
+token_scope = "token_public"
+
+{channel}
+"###
+            );
+            config_file.write_all(conf.as_bytes()).unwrap();
+
+            let result = Settings::new(file_path.as_path().to_str().unwrap());
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                format!("missing field `{field}`")
+            );
+        }
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_settings_from_file_unsupported_apprise_url() {
+        let tmp_dir = tempdir().unwrap();
+        let file_path = tmp_dir.path().join("config.toml");
+
+        let mut config_file = File::create(&file_path).unwrap();
+        let conf = r###"
+[kplc]
+basic_auth = "Basic asdasldkasdlasd"
+token_url = "https://selfservice.kplc.co.ke/api/token"
+bill_url = "https://selfservice.kplc.co.ke/api/publicData/2.0.1/"
+token_grant_type = "client_credentials"
+token_scope = "token_public"
+
+[apprise]
+urls = ["discord://4174216298/JHMHI8qBe7bk2ZwO5U711o3dV_js"]
+"###;
+        config_file.write_all(conf.as_bytes()).unwrap();
+
+        let result = Settings::new(file_path.as_path().to_str().unwrap());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("unsupported notification URL scheme: discord://"));
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_settings_from_file_zero_interval() {
+        let tmp_dir = tempdir().unwrap();
+        let file_path = tmp_dir.path().join("config.toml");
+
+        let mut config_file = File::create(&file_path).unwrap();
+        let conf = r###"
+[kplc]
+basic_auth = "Basic asdasldkasdlasd"
+token_url = "https://selfservice.kplc.co.ke/api/token"
+bill_url = "https://selfservice.kplc.co.ke/api/publicData/2.0.1/"
+token_grant_type = "client_credentials"
+token_scope = "token_public"
+
+[daemon]
+interval_minutes = 0
+"###;
+        config_file.write_all(conf.as_bytes()).unwrap();
+
+        let result = Settings::new(file_path.as_path().to_str().unwrap());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("interval_minutes must be at least 1"));
+
+        tmp_dir.close().unwrap();
+    }
 }
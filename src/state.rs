@@ -0,0 +1,356 @@
+use std::{cmp::Reverse, collections::BTreeMap, fs, io::ErrorKind, path::Path};
+
+use anyhow::{Context, Result};
+use chrono::prelude::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consumption::{self, ConsumptionPeriod},
+    kplc::KPLCBill,
+};
+
+// number of alert deliveries kept per account
+const MAX_DELIVERIES: usize = 20;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StateSettings {
+    pub path: String,
+}
+
+impl Default for StateSettings {
+    fn default() -> Self {
+        StateSettings {
+            path: "kplc-bill-alert-state.json".to_string(),
+        }
+    }
+}
+
+/// Data kept between runs, persisted as JSON.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct State {
+    #[serde(default)]
+    pub accounts: BTreeMap<String, AccountState>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AccountState {
+    #[serde(default)]
+    pub receipts: Vec<Receipt>,
+    /// Account balance as of the last bill fetched.
+    #[serde(default)]
+    pub balance: Option<Decimal>,
+    /// Bill number of the latest bill when a balance due was last alerted on.
+    #[serde(default)]
+    pub last_balance_due_bill: Option<String>,
+    /// Bill number of the latest bill when an overdue balance was last alerted on.
+    #[serde(default)]
+    pub last_overdue_bill: Option<String>,
+    /// Bill number of the last bill flagged as unusually high.
+    #[serde(default)]
+    pub last_unusual_bill: Option<String>,
+    /// Bill number of the last bill flagged as not matching the tariff.
+    #[serde(default)]
+    pub last_discrepant_bill: Option<String>,
+    /// Bill number of the latest bill when the budget was last found exceeded.
+    #[serde(default)]
+    pub last_over_budget_bill: Option<String>,
+    /// Bill number of the latest bill when credit was last found short of the next one.
+    #[serde(default)]
+    pub last_low_credit_bill: Option<String>,
+    /// Every bill seen on the account, newest first.
+    #[serde(default)]
+    pub bills: Vec<BillRecord>,
+    #[serde(default)]
+    pub readings: Vec<MeterReading>,
+    #[serde(default)]
+    pub consumption: Vec<ConsumptionPeriod>,
+    /// Date of the last reading flagged as estimated, per meter serial number.
+    #[serde(default)]
+    pub last_estimated_reading: BTreeMap<String, DateTime<Utc>>,
+    /// Most recent alert deliveries, newest first.
+    #[serde(default)]
+    pub deliveries: Vec<Delivery>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BillRecord {
+    pub bill_number: String,
+    pub billing_period: String,
+    pub from_date: DateTime<Utc>,
+    pub to_date: DateTime<Utc>,
+    pub due_date: DateTime<Utc>,
+    pub bill_amount: Decimal,
+    pub bill_pend_amount: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MeterReading {
+    pub serial_num: String,
+    pub reading_date: DateTime<Utc>,
+    pub reading_value: usize,
+    #[serde(default)]
+    pub estimated: bool,
+}
+
+/// An alert awaiting acknowledgement on the channel it was sent to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Receipt {
+    pub channel: String,
+    pub id: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// An alert sent, or attempted, on a channel.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Delivery {
+    pub channel: String,
+    pub title: String,
+    pub sent_at: DateTime<Utc>,
+    pub status: DeliveryStatus,
+    /// Receipt of the alert, if the channel issued one.
+    pub receipt: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Sent,
+    Failed,
+    AwaitingAcknowledgement,
+    Acknowledged,
+    Expired,
+    Cancelled,
+}
+
+impl AccountState {
+    /// Keeps track of a delivery, forgetting the oldest once there are
+    /// more than `MAX_DELIVERIES`.
+    pub fn record_delivery(&mut self, delivery: Delivery) {
+        self.deliveries.insert(0, delivery);
+        self.deliveries.truncate(MAX_DELIVERIES);
+    }
+
+    /// Updates the status of the delivery the receipt was issued for.
+    pub fn update_delivery(&mut self, receipt: &str, status: DeliveryStatus) {
+        if let Some(delivery) = self
+            .deliveries
+            .iter_mut()
+            .find(|delivery| delivery.receipt.as_deref() == Some(receipt))
+        {
+            delivery.status = status;
+        }
+    }
+
+    /// Adds the bills and meter readings in a freshly fetched bill to the
+    /// account's history, and works out consumption from the readings.
+    pub fn record(&mut self, bill: &KPLCBill) {
+        self.balance = Some(bill.data.balance);
+
+        for col_bill in bill.data.col_bills.iter() {
+            let record = BillRecord {
+                bill_number: col_bill.bill_number.clone(),
+                billing_period: col_bill.billing_period.clone(),
+                from_date: col_bill.from_date,
+                to_date: col_bill.to_date,
+                due_date: col_bill.due_date,
+                bill_amount: col_bill.bill_amount,
+                bill_pend_amount: col_bill.bill_pend_amount,
+            };
+
+            // the pending amount changes as bills get paid
+            match self
+                .bills
+                .iter_mut()
+                .find(|existing| existing.bill_number == record.bill_number)
+            {
+                Some(existing) => *existing = record,
+                None => self.bills.push(record),
+            }
+        }
+        self.bills.sort_by_key(|bill| Reverse(bill.to_date));
+
+        for meter in bill.data.meter_list.iter() {
+            for usage in meter.latest_usage_list.iter() {
+                let reading = MeterReading {
+                    serial_num: meter.serial_num.clone(),
+                    reading_date: usage.reading_date,
+                    reading_value: usage.reading_value,
+                    estimated: usage.estimated,
+                };
+
+                // an estimate may later be replaced by an actual reading
+                self.readings.retain(|existing| {
+                    existing.serial_num != reading.serial_num
+                        || existing.reading_date != reading.reading_date
+                });
+                self.readings.push(reading);
+            }
+        }
+        self.readings
+            .sort_by_key(|reading| Reverse(reading.reading_date));
+
+        self.consumption = consumption::compute(&self.readings, &self.bills);
+    }
+
+    /// Consumption over the period billed by the latest bill, per meter.
+    pub fn latest_consumption(&self) -> Vec<&ConsumptionPeriod> {
+        match self.bills.first() {
+            Some(latest_bill) => self
+                .consumption
+                .iter()
+                .filter(|period| period.bill_number.as_ref() == Some(&latest_bill.bill_number))
+                .collect(),
+            None => vec![],
+        }
+    }
+}
+
+impl State {
+    /// Loads state from `path`, starting afresh if the file doesn't exist yet.
+    pub fn load(path: &str) -> Result<State> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .with_context(|| format!("failed to parse state file {path}")),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(State::default()),
+            Err(err) => Err(err).with_context(|| format!("failed to read state file {path}")),
+        }
+    }
+
+    /// Writes state to `path`, going through a temporary file so that a crash
+    /// midway doesn't leave it truncated.
+    pub fn save(&self, path: &str) -> Result<()> {
+        let tmp_path = format!("{path}.tmp");
+        let contents = serde_json::to_string_pretty(self)?;
+
+        fs::write(&tmp_path, contents)
+            .with_context(|| format!("failed to write state file {tmp_path}"))?;
+        fs::rename(&tmp_path, Path::new(path))
+            .with_context(|| format!("failed to write state file {path}"))?;
+
+        Ok(())
+    }
+
+    pub fn account(&mut self, account_number: &str) -> &mut AccountState {
+        self.accounts.entry(account_number.to_string()).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    use super::{AccountState, Delivery, DeliveryStatus, Receipt, State};
+    use crate::test_utils::get_kplc_bill_resp;
+
+    #[test]
+    fn test_record_bill_history() {
+        let mut account_state = AccountState::default();
+
+        // the previous month's fetch, a reading earlier
+        let mut bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        let usage = &mut bill.data.meter_list[0].latest_usage_list[0];
+        usage.reading_date -= Duration::days(28);
+        usage.reading_value -= 523;
+        account_state.record(&bill);
+        assert!(account_state.consumption.is_empty());
+
+        let bill = get_kplc_bill_resp("kplc_bill_balance.json");
+        account_state.record(&bill);
+        account_state.record(&bill);
+
+        assert_eq!(account_state.bills.len(), bill.data.col_bills.len());
+        assert_eq!(account_state.bills[0].bill_number, "981239123213");
+        assert_eq!(account_state.readings.len(), 2);
+        assert_eq!(account_state.readings[0].reading_value, 18234);
+
+        let latest_consumption = account_state.latest_consumption();
+        assert_eq!(latest_consumption.len(), 1);
+        assert_eq!(latest_consumption[0].kwh, 523);
+        assert_eq!(
+            latest_consumption[0].bill_number.as_deref(),
+            Some("981239123213")
+        );
+    }
+
+    #[test]
+    fn test_record_deliveries() {
+        let mut account_state = AccountState::default();
+        for i in 0..25 {
+            account_state.record_delivery(Delivery {
+                channel: "pushover".to_string(),
+                title: "KPLC Bill (#1234567): 10 - October 2022".to_string(),
+                sent_at: Utc::now(),
+                status: DeliveryStatus::AwaitingAcknowledgement,
+                receipt: Some(format!("receipt-{i}")),
+            });
+        }
+
+        assert_eq!(account_state.deliveries.len(), 20);
+        assert_eq!(
+            account_state.deliveries[0].receipt.as_deref(),
+            Some("receipt-24")
+        );
+
+        account_state.update_delivery("receipt-24", DeliveryStatus::Acknowledged);
+        assert_eq!(
+            account_state.deliveries[0].status,
+            DeliveryStatus::Acknowledged
+        );
+        assert_eq!(
+            account_state.deliveries[1].status,
+            DeliveryStatus::AwaitingAcknowledgement
+        );
+    }
+
+    #[test]
+    fn test_load_missing_state_file() {
+        let tmp_dir = tempdir().unwrap();
+        let file_path = tmp_dir.path().join("state.json");
+
+        let state = State::load(file_path.to_str().unwrap()).unwrap();
+        assert!(state.accounts.is_empty());
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_save_and_load_state() {
+        let tmp_dir = tempdir().unwrap();
+        let file_path = tmp_dir.path().join("state.json");
+        let path = file_path.to_str().unwrap();
+
+        let receipt = Receipt {
+            channel: "Pushover".to_string(),
+            id: "rLqVuqTRh62UzxtmqiaLzQmVcPgiCy".to_string(),
+            sent_at: Utc.with_ymd_and_hms(2022, 10, 12, 8, 0, 0).unwrap(),
+        };
+        let mut state = State::default();
+        state.account("1234567").receipts.push(receipt.clone());
+        state.save(path).unwrap();
+
+        let mut state = State::load(path).unwrap();
+        assert_eq!(state.account("1234567").receipts, vec![receipt]);
+
+        tmp_dir.close().unwrap();
+    }
+
+    #[test]
+    fn test_load_invalid_state_file() {
+        let tmp_dir = tempdir().unwrap();
+        let file_path = tmp_dir.path().join("state.json");
+        let path = file_path.to_str().unwrap();
+        std::fs::write(path, "not json").unwrap();
+
+        let result = State::load(path);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!("failed to parse state file {path}")
+        );
+
+        tmp_dir.close().unwrap();
+    }
+}
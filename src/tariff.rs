@@ -0,0 +1,293 @@
+use std::fs;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use config::{Config, File, FileFormat};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::Deserialize;
+
+use crate::state::{AccountState, BillRecord};
+
+// tariffs shipped with the binary, used unless a tariffs file is configured
+static DEFAULT_TARIFFS: &str = include_str!("../resources/tariffs.toml");
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TariffSettings {
+    /// Tariffs file to use instead of the built-in one.
+    pub path: Option<String>,
+    /// How far off, in percent, a bill may be from the expected amount.
+    #[serde(default = "default_tolerance_percent")]
+    pub tolerance_percent: f64,
+}
+
+fn default_tolerance_percent() -> f64 {
+    5.0
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Tariffs {
+    tariffs: Vec<Tariff>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Tariff {
+    pub effective_from: NaiveDate,
+    pub bands: Vec<TariffBand>,
+    #[serde(default)]
+    pub tiered: bool,
+    /// Pass-through charges, in KES per kWh.
+    pub fuel_energy_cost: Decimal,
+    pub forex: Decimal,
+    pub inflation_adjustment: Decimal,
+    /// Levies, in KES per kWh.
+    pub wra_levy: Decimal,
+    pub epra_levy: Decimal,
+    /// Rural Electrification Programme levy, as a percentage of the energy charge.
+    pub rep_levy_percent: Decimal,
+    pub vat_percent: Decimal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TariffBand {
+    /// Upper limit of the band, or `None` for the last band.
+    pub up_to_kwh: Option<usize>,
+    pub rate: Decimal,
+}
+
+/// A bill that doesn't match what the tariff says it should be.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discrepancy {
+    pub bill_number: String,
+    pub billing_period: String,
+    pub kwh: usize,
+    pub bill_amount: Decimal,
+    pub expected_amount: Decimal,
+    pub tariff_effective_from: NaiveDate,
+}
+
+impl Discrepancy {
+    pub fn difference_percent(&self) -> f64 {
+        let expected_amount = self.expected_amount.to_f64().unwrap_or_default();
+        let bill_amount = self.bill_amount.to_f64().unwrap_or_default();
+
+        (bill_amount - expected_amount) / expected_amount * 100.0
+    }
+}
+
+impl Tariffs {
+    pub fn load(path: Option<&str>) -> Result<Tariffs> {
+        let contents = match path {
+            Some(path) => fs::read_to_string(path)
+                .with_context(|| format!("failed to read tariffs file {path}"))?,
+            None => DEFAULT_TARIFFS.to_string(),
+        };
+
+        let tariffs: Tariffs = Config::builder()
+            .add_source(File::from_str(&contents, FileFormat::Toml))
+            .build()?
+            .try_deserialize()
+            .context("failed to parse tariffs")?;
+
+        if tariffs.tariffs.iter().any(|tariff| tariff.bands.is_empty()) {
+            return Err(anyhow!("failed to parse tariffs: every tariff needs bands"));
+        }
+
+        Ok(tariffs)
+    }
+
+    /// The latest tariff in effect on `date`.
+    pub fn effective_on(&self, date: NaiveDate) -> Option<&Tariff> {
+        self.tariffs
+            .iter()
+            .filter(|tariff| tariff.effective_from <= date)
+            .max_by_key(|tariff| tariff.effective_from)
+    }
+}
+
+impl Tariff {
+    fn energy_charge(&self, kwh: usize) -> Decimal {
+        if !self.tiered {
+            let band = self
+                .bands
+                .iter()
+                .find(|band| band.up_to_kwh.is_none_or(|up_to_kwh| kwh <= up_to_kwh))
+                .unwrap_or_else(|| self.bands.last().unwrap());
+
+            return band.rate * Decimal::from(kwh);
+        }
+
+        let mut charge = Decimal::ZERO;
+        let mut band_start = 0;
+        for band in self.bands.iter() {
+            let band_end = band.up_to_kwh.unwrap_or(usize::MAX).min(kwh);
+            if band_end > band_start {
+                charge += band.rate * Decimal::from(band_end - band_start);
+                band_start = band_end;
+            }
+        }
+
+        charge
+    }
+
+    /// Expected bill, VAT inclusive, for consuming `kwh` under this tariff.
+    pub fn bill_for(&self, kwh: usize) -> Decimal {
+        let units = Decimal::from(kwh);
+        let hundred = Decimal::ONE_HUNDRED;
+
+        let energy_charge = self.energy_charge(kwh);
+        let pass_through = (self.fuel_energy_cost + self.forex + self.inflation_adjustment) * units;
+        let levies = (self.wra_levy + self.epra_levy) * units
+            + energy_charge * self.rep_levy_percent / hundred;
+        let vat = (energy_charge + pass_through) * self.vat_percent / hundred;
+
+        (energy_charge + pass_through + levies + vat).round_dp(2)
+    }
+}
+
+/// Checks the latest bill against the bill expected from the kWh consumed
+/// over its billing period, across all the account's meters.
+pub fn verify(
+    account_state: &AccountState,
+    tariffs: &Tariffs,
+    settings: &TariffSettings,
+) -> Option<Discrepancy> {
+    let latest_bill: &BillRecord = account_state.bills.first()?;
+    let periods = account_state.latest_consumption();
+    if periods.is_empty() {
+        return None;
+    }
+
+    let kwh: usize = periods.iter().map(|period| period.kwh).sum();
+    let tariff = tariffs.effective_on(latest_bill.to_date.date_naive())?;
+    let discrepancy = Discrepancy {
+        bill_number: latest_bill.bill_number.clone(),
+        billing_period: latest_bill.billing_period.clone(),
+        kwh,
+        bill_amount: latest_bill.bill_amount,
+        expected_amount: tariff.bill_for(kwh),
+        tariff_effective_from: tariff.effective_from,
+    };
+
+    if discrepancy.expected_amount.is_zero()
+        || discrepancy.difference_percent().abs() <= settings.tolerance_percent
+    {
+        return None;
+    }
+
+    Some(discrepancy)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+
+    use super::{verify, TariffSettings, Tariffs};
+    use crate::{
+        consumption,
+        state::{AccountState, BillRecord, MeterReading},
+    };
+
+    fn make_account_state(bill_amount: Decimal) -> AccountState {
+        let mut account_state = AccountState {
+            bills: vec![BillRecord {
+                bill_number: "981239123213".to_string(),
+                billing_period: "10 - October 2023".to_string(),
+                from_date: Utc.with_ymd_and_hms(2023, 9, 7, 21, 0, 0).unwrap(),
+                to_date: Utc.with_ymd_and_hms(2023, 10, 4, 21, 0, 0).unwrap(),
+                due_date: Utc.with_ymd_and_hms(2023, 10, 25, 21, 0, 0).unwrap(),
+                bill_amount,
+                bill_pend_amount: bill_amount,
+            }],
+            readings: vec![
+                MeterReading {
+                    serial_num: "981928391283".to_string(),
+                    reading_date: Utc.with_ymd_and_hms(2023, 10, 4, 21, 0, 0).unwrap(),
+                    reading_value: 18234,
+                    estimated: false,
+                },
+                MeterReading {
+                    serial_num: "981928391283".to_string(),
+                    reading_date: Utc.with_ymd_and_hms(2023, 9, 6, 21, 0, 0).unwrap(),
+                    reading_value: 18084,
+                    estimated: false,
+                },
+            ],
+            ..Default::default()
+        };
+        account_state.consumption =
+            consumption::compute(&account_state.readings, &account_state.bills);
+
+        account_state
+    }
+
+    fn make_settings() -> TariffSettings {
+        TariffSettings {
+            path: None,
+            tolerance_percent: 5.0,
+        }
+    }
+
+    #[test]
+    fn test_effective_tariff() {
+        let tariffs = Tariffs::load(None).unwrap();
+
+        let tariff = tariffs
+            .effective_on(NaiveDate::from_ymd_opt(2023, 10, 4).unwrap())
+            .unwrap();
+        assert_eq!(
+            tariff.effective_from,
+            NaiveDate::from_ymd_opt(2023, 4, 1).unwrap()
+        );
+
+        let tariff = tariffs
+            .effective_on(NaiveDate::from_ymd_opt(2022, 10, 4).unwrap())
+            .unwrap();
+        assert_eq!(
+            tariff.effective_from,
+            NaiveDate::from_ymd_opt(2018, 11, 1).unwrap()
+        );
+
+        assert!(tariffs
+            .effective_on(NaiveDate::from_ymd_opt(2017, 1, 1).unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn test_bill_for() {
+        let tariffs = Tariffs::load(None).unwrap();
+        let mut tariff = tariffs
+            .effective_on(NaiveDate::from_ymd_opt(2023, 10, 4).unwrap())
+            .unwrap()
+            .clone();
+
+        // 150 kWh at 20.97 = 3145.50 energy, 969 pass-through, 15 + 157.275
+        // levies and 658.32 VAT
+        assert_eq!(tariff.bill_for(150), Decimal::new(494510, 2));
+
+        // 30 at 12.22 + 70 at 16.54 + 50 at 20.97 = 2572.90 energy
+        tariff.tiered = true;
+        assert_eq!(tariff.bill_for(150), Decimal::new(425225, 2));
+    }
+
+    #[test]
+    fn test_verify_matching_bill() {
+        let tariffs = Tariffs::load(None).unwrap();
+        let account_state = make_account_state(Decimal::new(4950, 0));
+
+        assert_eq!(verify(&account_state, &tariffs, &make_settings()), None);
+    }
+
+    #[test]
+    fn test_verify_discrepant_bill() {
+        let tariffs = Tariffs::load(None).unwrap();
+        let account_state = make_account_state(Decimal::new(7500, 0));
+
+        let discrepancy = verify(&account_state, &tariffs, &make_settings()).unwrap();
+        assert_eq!(discrepancy.bill_number, "981239123213");
+        assert_eq!(discrepancy.kwh, 150);
+        assert_eq!(discrepancy.expected_amount, Decimal::new(494510, 2));
+        assert_eq!(format!("{:.0}", discrepancy.difference_percent()), "52");
+    }
+}
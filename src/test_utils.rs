@@ -0,0 +1,13 @@
+use std::{env, fs::File, path::Path};
+
+use crate::kplc::KPLCBill;
+
+/// Reads a KPLC bill response from `resources/test`.
+pub fn get_kplc_bill_resp(filename: &str) -> KPLCBill {
+    let base_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let filepath = format!("{base_dir}/resources/test/{filename}");
+    let path = Path::new(filepath.as_str());
+    let file = File::open(path).unwrap();
+
+    serde_json::from_reader(file).unwrap()
+}